@@ -1,5 +1,57 @@
 use crate::value::Value;
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::any::Any;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::rc::{Rc, Weak};
+
+/// Executor que o interpretador registra com `stdlib::init` para que funções nativas
+/// possam chamar funções Snask (`Value::Function`) recebidas como argumento.
+pub type FunctionCaller = Rc<dyn Fn(&Value, Vec<Value>) -> Result<Value, String>>;
+
+thread_local! {
+    static FUNCTION_CALLER: RefCell<Option<FunctionCaller>> = RefCell::new(None);
+}
+
+/// Troca o executor da thread atual, retornando o anterior.
+pub fn set_function_caller(caller: FunctionCaller) -> Option<FunctionCaller> {
+    FUNCTION_CALLER.with(|slot| slot.borrow_mut().replace(caller))
+}
+
+/// Verifica se o valor pode ser chamado com `call_function`.
+pub fn is_callable(value: &Value) -> bool {
+    matches!(value, Value::Function(_) | Value::NativeFunction(_))
+}
+
+/// Chama uma função Snask ou nativa a partir de código nativo.
+/// Erros lançados pela função chamada são propagados sem alteração.
+pub fn call_function(func: &Value, args: Vec<Value>) -> Result<Value, String> {
+    match func {
+        Value::NativeFunction(native) => native(args),
+        Value::Function(_) => {
+            // Clona o executor antes de chamar: a função do usuário pode chamar
+            // outra função nativa que também precise dele (ex.: map dentro de map).
+            let caller = FUNCTION_CALLER.with(|slot| slot.borrow().clone());
+            match caller {
+                Some(caller) => caller(func, args),
+                None => Err("stdlib::init não foi chamado nesta thread; nenhum interpretador para chamar funções".to_string()),
+            }
+        },
+        _ => Err("valor não é uma função".to_string()),
+    }
+}
+
+/// Regra de verdade usada pelos predicados: `nil` e `false` são falsos, o resto é verdadeiro.
+pub fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Nil => false,
+        Value::Boolean(b) => *b,
+        _ => true,
+    }
+}
 
 /// Cria e retorna o objeto do módulo `collections` com todas as suas funções.
 pub fn create_module() -> Value {
@@ -9,12 +61,17 @@ pub fn create_module() -> Value {
         if args.len() != 2 { return Err("map espera 2 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(list), Value::Function(_func_decl)) => {
-                // TODO: Implementar chamada de função para cada elemento
-                // Por enquanto, retorna a lista original
-                Ok(Value::List(list.clone()))
+            (Value::List(list), func) if is_callable(func) => {
+                let mut mapped = Vec::with_capacity(list.len());
+                for item in list {
+                    mapped.push(call_function(func, vec![item.clone()])?);
+                }
+                Ok(Value::List(mapped))
             },
-            _ => Err("map espera uma lista e uma função".to_string()),
+            (source, func) if is_iterator(source) && is_callable(func) => {
+                Ok(new_iterator("map", vec![("source", source.clone()), ("func", func.clone())]))
+            },
+            _ => Err("map espera uma lista (ou iterador) e uma função".to_string()),
         }
     }));
 
@@ -22,23 +79,41 @@ pub fn create_module() -> Value {
         if args.len() != 2 { return Err("filter espera 2 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(list), Value::Function(_func_decl)) => {
-                // TODO: Implementar filtragem com função
-                Ok(Value::List(list.clone()))
+            (Value::List(list), func) if is_callable(func) => {
+                let mut filtered = Vec::new();
+                for item in list {
+                    if is_truthy(&call_function(func, vec![item.clone()])?) {
+                        filtered.push(item.clone());
+                    }
+                }
+                Ok(Value::List(filtered))
             },
-            _ => Err("filter espera uma lista e uma função".to_string()),
+            (source, func) if is_iterator(source) && is_callable(func) => {
+                Ok(new_iterator("filter", vec![("source", source.clone()), ("func", func.clone())]))
+            },
+            _ => Err("filter espera uma lista (ou iterador) e uma função".to_string()),
         }
     }));
 
     module.insert("reduce".to_string(), Value::NativeFunction(|args| {
-        if args.len() < 2 { return Err("reduce espera pelo menos 2 argumentos".to_string()); }
+        if args.len() < 2 || args.len() > 3 { return Err("reduce espera 2 ou 3 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(_list), Value::Function(_func_decl)) => {
-                // TODO: Implementar reduce com função
-                Ok(Value::Nil)
+            (source, func) if is_iterable(source) && is_callable(func) => {
+                let mut items = open_iter(source)?;
+                let mut acc = match args.get(2) {
+                    Some(initial) => initial.clone(),
+                    None => match items.next() {
+                        Some(first) => first?,
+                        None => return Err("reduce de lista vazia sem valor inicial".to_string()),
+                    },
+                };
+                for item in items {
+                    acc = call_function(func, vec![acc, item?])?;
+                }
+                Ok(acc)
             },
-            _ => Err("reduce espera uma lista e uma função".to_string()),
+            _ => Err("reduce espera uma lista (ou iterador) e uma função".to_string()),
         }
     }));
 
@@ -46,11 +121,16 @@ pub fn create_module() -> Value {
         if args.len() != 2 { return Err("find espera 2 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(_list), Value::Function(_func_decl)) => {
-                // TODO: Implementar find com função
+            (source, func) if is_iterable(source) && is_callable(func) => {
+                for item in open_iter(source)? {
+                    let item = item?;
+                    if is_truthy(&call_function(func, vec![item.clone()])?) {
+                        return Ok(item);
+                    }
+                }
                 Ok(Value::Nil)
             },
-            _ => Err("find espera uma lista e uma função".to_string()),
+            _ => Err("find espera uma lista (ou iterador) e uma função".to_string()),
         }
     }));
 
@@ -58,11 +138,15 @@ pub fn create_module() -> Value {
         if args.len() != 2 { return Err("any espera 2 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(_list), Value::Function(_func_decl)) => {
-                // TODO: Implementar any com função
+            (source, func) if is_iterable(source) && is_callable(func) => {
+                for item in open_iter(source)? {
+                    if is_truthy(&call_function(func, vec![item?])?) {
+                        return Ok(Value::Boolean(true));
+                    }
+                }
                 Ok(Value::Boolean(false))
             },
-            _ => Err("any espera uma lista e uma função".to_string()),
+            _ => Err("any espera uma lista (ou iterador) e uma função".to_string()),
         }
     }));
 
@@ -70,14 +154,205 @@ pub fn create_module() -> Value {
         if args.len() != 2 { return Err("all espera 2 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
-            (Value::List(_list), Value::Function(_func_decl)) => {
-                // TODO: Implementar all com função
+            (source, func) if is_iterable(source) && is_callable(func) => {
+                for item in open_iter(source)? {
+                    if !is_truthy(&call_function(func, vec![item?])?) {
+                        return Ok(Value::Boolean(false));
+                    }
+                }
                 Ok(Value::Boolean(true))
             },
-            _ => Err("all espera uma lista e uma função".to_string()),
+            _ => Err("all espera uma lista (ou iterador) e uma função".to_string()),
+        }
+    }));
+
+    // Iteradores preguiçosos. Os adaptadores abaixo aceitam listas ou iteradores:
+    // se alguma entrada for um iterador o resultado também é (nada é calculado até
+    // `collect`); se todas forem listas o resultado é materializado na hora.
+    module.insert("iter".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("iter espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::List(list) => Ok(new_iterator("list", vec![("items", Value::List(list.clone()))])),
+            source if is_iterator(source) => Ok(source.clone()),
+            source if is_iterable(source) => {
+                let items = open_iter(source)?.collect::<Result<Vec<_>, _>>()?;
+                Ok(new_iterator("list", vec![("items", Value::List(items))]))
+            },
+            _ => Err("iter espera uma lista, conjunto, dicionário ou iterador".to_string()),
+        }
+    }));
+
+    module.insert("count".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 2 { return Err("count espera no máximo 2 argumentos".to_string()); }
+        
+        let start = match args.first() {
+            None => 0.0,
+            Some(Value::Number(n)) => *n,
+            Some(_) => return Err("count espera números".to_string()),
+        };
+        let step = match args.get(1) {
+            None => 1.0,
+            Some(Value::Number(n)) => *n,
+            Some(_) => return Err("count espera números".to_string()),
+        };
+        if !start.is_finite() || !step.is_finite() {
+            return Err("count espera números finitos".to_string());
+        }
+        if step == 0.0 {
+            return Err("step não pode ser zero".to_string());
+        }
+        
+        // Sem "end": sequência infinita, use com take/take_while.
+        Ok(RangeSpec { start, end: None, step, inclusive: false }.to_object())
+    }));
+
+    module.insert("len".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("len espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::List(list) => Ok(Value::Number(list.len() as f64)),
+            source if is_range(source) => match RangeSpec::from_object(source)?.len() {
+                Some(len) => Ok(Value::Number(len as f64)),
+                None => Err("len: a sequência é infinita".to_string()),
+            },
+            source if is_set(source) => Ok(Value::Number(set_items("len", source)?.len() as f64)),
+            source if object_kind(source) == Some("deque") => {
+                Ok(Value::Number(with_deque("len", source, |deque| deque.len())? as f64))
+            },
+            source if object_kind(source) == Some("heap") => {
+                Ok(Value::Number(with_heap("len", source, |state| state.heap.len())? as f64))
+            },
+            source if is_iterator(source) => Err("len não pode medir um iterador sem consumi-lo; use collect".to_string()),
+            Value::Dict(map) => Ok(Value::Number(map.len() as f64)),
+            _ => Err("len espera uma lista, conjunto, dicionário ou range".to_string()),
+        }
+    }));
+
+    module.insert("contains".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("contains espera 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::List(list) => Ok(Value::Boolean(list.contains(&args[1]))),
+            source if is_range(source) => match &args[1] {
+                Value::Number(n) => Ok(Value::Boolean(RangeSpec::from_object(source)?.contains(*n))),
+                _ => Ok(Value::Boolean(false)),
+            },
+            source if is_set(source) => Ok(Value::Boolean(set_items("contains", source)?.contains(&args[1]))),
+            source if is_iterator(source) => {
+                for item in open_iter(source)? {
+                    if item? == args[1] {
+                        return Ok(Value::Boolean(true));
+                    }
+                }
+                Ok(Value::Boolean(false))
+            },
+            _ => Err("contains espera uma lista, conjunto ou iterador".to_string()),
+        }
+    }));
+
+    module.insert("nth".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("nth espera 2 argumentos".to_string()); }
+        
+        let index = match &args[1] {
+            Value::Number(n) if n.fract() == 0.0 => *n,
+            _ => return Err("nth espera um índice inteiro".to_string()),
+        };
+        // Índices negativos contam a partir do fim (só em coleções finitas).
+        let resolve = |len: Option<u64>| -> Option<u64> {
+            match len {
+                _ if index >= 0.0 => Some(index as u64).filter(|i| len.is_none_or(|len| *i < len)),
+                Some(len) if -index <= len as f64 => Some(len - (-index) as u64),
+                _ => None,
+            }
+        };
+
+        match &args[0] {
+            Value::List(list) => {
+                Ok(resolve(Some(list.len() as u64)).map(|i| list[i as usize].clone()).unwrap_or(Value::Nil))
+            },
+            source if is_range(source) => {
+                let range = RangeSpec::from_object(source)?;
+                Ok(resolve(range.len()).map(|i| Value::Number(range.get(i))).unwrap_or(Value::Nil))
+            },
+            source if is_iterable(source) => {
+                if index < 0.0 {
+                    return Err("nth não aceita índices negativos em iteradores".to_string());
+                }
+                match open_iter(source)?.nth(index as usize) {
+                    Some(item) => item,
+                    None => Ok(Value::Nil),
+                }
+            },
+            _ => Err("nth espera uma lista, range ou iterador".to_string()),
+        }
+    }));
+
+    module.insert("take".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("take espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (source, Value::Number(n)) if is_iterable(source) && *n >= 0.0 => {
+                adapt(&args[..1], new_iterator("take", vec![("source", source.clone()), ("n", Value::Number(n.floor()))]))
+            },
+            _ => Err("take espera uma lista (ou iterador) e um número não negativo".to_string()),
+        }
+    }));
+
+    module.insert("skip".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("skip espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (source, Value::Number(n)) if is_iterable(source) && *n >= 0.0 => {
+                adapt(&args[..1], new_iterator("skip", vec![("source", source.clone()), ("n", Value::Number(n.floor()))]))
+            },
+            _ => Err("skip espera uma lista (ou iterador) e um número não negativo".to_string()),
+        }
+    }));
+
+    module.insert("take_while".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("take_while espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (source, func) if is_iterable(source) && is_callable(func) => {
+                adapt(&args[..1], new_iterator("take_while", vec![("source", source.clone()), ("func", func.clone())]))
+            },
+            _ => Err("take_while espera uma lista (ou iterador) e uma função".to_string()),
+        }
+    }));
+
+    module.insert("enumerate".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("enumerate espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            source if is_iterable(source) => {
+                adapt(&args, new_iterator("enumerate", vec![("source", source.clone())]))
+            },
+            _ => Err("enumerate espera uma lista ou iterador".to_string()),
+        }
+    }));
+
+    module.insert("zip".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 { return Err("zip espera pelo menos 2 argumentos".to_string()); }
+        if !args.iter().all(is_iterable) {
+            return Err("zip espera listas ou iteradores".to_string());
+        }
+        
+        adapt(&args, new_iterator("zip", vec![("sources", Value::List(args.clone()))]))
+    }));
+
+    module.insert("chain".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() { return Err("chain espera pelo menos 1 argumento".to_string()); }
+        if !args.iter().all(is_iterable) {
+            return Err("chain espera listas ou iteradores".to_string());
         }
+        
+        adapt(&args, new_iterator("chain", vec![("sources", Value::List(args.clone()))]))
     }));
 
+    module.insert("collect".to_string(), Value::NativeFunction(collect_iterator));
+    module.insert("to_list".to_string(), Value::NativeFunction(collect_iterator));
+
     module.insert("reverse".to_string(), Value::NativeFunction(|args| {
         if args.len() != 1 { return Err("reverse espera 1 argumento".to_string()); }
         
@@ -92,112 +367,1711 @@ pub fn create_module() -> Value {
     }));
 
     module.insert("sort".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 1 { return Err("sort espera 1 argumento".to_string()); }
+        if args.is_empty() || args.len() > 3 { return Err("sort espera 1, 2 ou 3 argumentos".to_string()); }
         
-        match &args[0] {
-            Value::List(list) => {
-                let mut sorted = list.clone();
-                sorted.sort_by(|a, b| {
-                    match (a, b) {
-                        (Value::Number(n1), Value::Number(n2)) => {
-                            n1.partial_cmp(n2).unwrap_or(std::cmp::Ordering::Equal)
-                        },
-                        (Value::String(s1), Value::String(s2)) => s1.cmp(s2),
-                        _ => std::cmp::Ordering::Equal,
-                    }
-                });
+        let list = match &args[0] {
+            Value::List(list) => list,
+            _ => return Err("sort espera uma lista".to_string()),
+        };
+        let keys = parse_sort_keys(args.get(1).unwrap_or(&Value::Nil))?;
+        let descending = match args.get(2) {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("sort espera um booleano (descending) como terceiro argumento".to_string()),
+        };
+
+        // Calcula as chaves uma única vez por elemento (decorate-sort-undecorate).
+        let mut decorated = Vec::with_capacity(list.len());
+        for item in list {
+            let mut item_keys = Vec::with_capacity(keys.len());
+            for (key_fn, _) in &keys {
+                item_keys.push(call_function(key_fn, vec![item.clone()])?);
+            }
+            decorated.push((item_keys, item.clone()));
+        }
+
+        // `sort_by` é estável; inverter o comparador (e não a lista) mantém a
+        // ordem original dos elementos iguais também no modo decrescente.
+        decorated.sort_by(|(keys_a, a), (keys_b, b)| {
+            let ordering = if keys.is_empty() {
+                compare_values(a, b)
+            } else {
+                keys.iter()
+                    .zip(keys_a.iter().zip(keys_b))
+                    .map(|((_, key_desc), (ka, kb))| {
+                        let ordering = compare_values(ka, kb);
+                        if *key_desc { ordering.reverse() } else { ordering }
+                    })
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            };
+            if descending { ordering.reverse() } else { ordering }
+        });
+
+        Ok(Value::List(decorated.into_iter().map(|(_, item)| item).collect()))
+    }));
+
+    module.insert("sort_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("sort_by espera 2 ou 3 argumentos".to_string()); }
+        
+        let descending = match args.get(2) {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("sort_by espera um booleano (descending) como terceiro argumento".to_string()),
+        };
+
+        match (&args[0], &args[1]) {
+            (Value::List(list), cmp_fn) if is_callable(cmp_fn) => {
+                let sorted = try_merge_sort(list.clone(), &mut |a, b| {
+                    let ordering = match call_function(cmp_fn, vec![a.clone(), b.clone()])? {
+                        Value::Number(n) if n < 0.0 => Ordering::Less,
+                        Value::Number(n) if n > 0.0 => Ordering::Greater,
+                        Value::Number(_) => Ordering::Equal,
+                        _ => return Err("sort_by espera que o comparador retorne um número".to_string()),
+                    };
+                    Ok(if descending { ordering.reverse() } else { ordering })
+                })?;
                 Ok(Value::List(sorted))
             },
-            _ => Err("sort espera uma lista".to_string()),
+            _ => Err("sort_by espera uma lista e uma função".to_string()),
         }
     }));
 
+    module.insert("compare".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("compare espera 2 argumentos".to_string()); }
+        
+        let result = match compare_values(&args[0], &args[1]) {
+            Ordering::Less => -1.0,
+            Ordering::Equal => 0.0,
+            Ordering::Greater => 1.0,
+        };
+        Ok(Value::Number(result))
+    }));
+
     module.insert("unique".to_string(), Value::NativeFunction(|args| {
         if args.len() != 1 { return Err("unique espera 1 argumento".to_string()); }
         
         match &args[0] {
-            Value::List(list) => {
+            Value::List(list) => Ok(Value::List(unique_values(list.iter().cloned()))),
+            _ => Err("unique espera uma lista".to_string()),
+        }
+    }));
+
+    module.insert("unique_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("unique_by espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (Value::List(list), key_fn) if is_callable(key_fn) => {
+                let mut seen = HashSet::new();
                 let mut unique_list = Vec::new();
                 for item in list {
-                    if !unique_list.contains(item) {
+                    if seen.insert(call_function(key_fn, vec![item.clone()])?) {
                         unique_list.push(item.clone());
                     }
                 }
                 Ok(Value::List(unique_list))
             },
-            _ => Err("unique espera uma lista".to_string()),
+            _ => Err("unique_by espera uma lista e uma função".to_string()),
+        }
+    }));
+
+    // Conjuntos. Guardam os elementos sem repetição, na ordem de inserção, e
+    // consultam por hash. `set_add`/`set_remove` alteram o conjunto e o retornam;
+    // as demais operações aceitam listas ou conjuntos e devolvem um conjunto novo
+    // se alguma entrada for um conjunto, ou uma lista se todas forem listas.
+    module.insert("set".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 1 { return Err("set espera no máximo 1 argumento".to_string()); }
+        
+        match args.first() {
+            None => Ok(new_set(Vec::new())),
+            Some(source) if is_iterable(source) => {
+                let items = open_iter(source)?.collect::<Result<Vec<_>, _>>()?;
+                Ok(new_set(unique_values(items)))
+            },
+            Some(_) => Err("set espera uma lista ou iterador".to_string()),
+        }
+    }));
+
+    module.insert("set_add".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("set_add espera 2 argumentos".to_string()); }
+        
+        with_set("set_add", &args[0], |set| set.insert(args[1].clone()))?;
+        Ok(args[0].clone())
+    }));
+
+    module.insert("set_remove".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("set_remove espera 2 argumentos".to_string()); }
+        
+        with_set("set_remove", &args[0], |set| set.remove(&args[1]))?;
+        Ok(args[0].clone())
+    }));
+
+    module.insert("set_has".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("set_has espera 2 argumentos".to_string()); }
+        
+        Ok(Value::Boolean(with_set("set_has", &args[0], |set| set.index.contains_key(&args[1]))?))
+    }));
+
+    module.insert("set_len".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("set_len espera 1 argumento".to_string()); }
+        
+        Ok(Value::Number(with_set("set_len", &args[0], |set| set.index.len())? as f64))
+    }));
+
+    module.insert("union".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 { return Err("union espera pelo menos 2 argumentos".to_string()); }
+        
+        let mut all = Vec::new();
+        for arg in &args {
+            all.extend(set_items("union", arg)?);
+        }
+        Ok(set_result(&args, unique_values(all)))
+    }));
+
+    module.insert("intersection".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 { return Err("intersection espera pelo menos 2 argumentos".to_string()); }
+        
+        let mut result = unique_values(set_items("intersection", &args[0])?);
+        for arg in &args[1..] {
+            let other: HashSet<Value> = set_items("intersection", arg)?.into_iter().collect();
+            result.retain(|item| other.contains(item));
         }
+        Ok(set_result(&args, result))
+    }));
+
+    module.insert("difference".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("difference espera 2 argumentos".to_string()); }
+        
+        let other: HashSet<Value> = set_items("difference", &args[1])?.into_iter().collect();
+        let mut result = unique_values(set_items("difference", &args[0])?);
+        result.retain(|item| !other.contains(item));
+        Ok(set_result(&args, result))
+    }));
+
+    module.insert("symmetric_difference".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("symmetric_difference espera 2 argumentos".to_string()); }
+        
+        let left = unique_values(set_items("symmetric_difference", &args[0])?);
+        let right = unique_values(set_items("symmetric_difference", &args[1])?);
+        let left_set: HashSet<&Value> = left.iter().collect();
+        let right_set: HashSet<&Value> = right.iter().collect();
+        let result = left.iter()
+            .filter(|item| !right_set.contains(item))
+            .chain(right.iter().filter(|item| !left_set.contains(item)))
+            .cloned()
+            .collect();
+        Ok(set_result(&args, result))
+    }));
+
+    module.insert("is_subset".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("is_subset espera 2 argumentos".to_string()); }
+        
+        let other: HashSet<Value> = set_items("is_subset", &args[1])?.into_iter().collect();
+        let items = set_items("is_subset", &args[0])?;
+        Ok(Value::Boolean(items.iter().all(|item| other.contains(item))))
     }));
 
     module.insert("flatten".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 1 { return Err("flatten espera 1 argumento".to_string()); }
+        if args.is_empty() || args.len() > 2 { return Err("flatten espera 1 ou 2 argumentos".to_string()); }
         
+        let depth = match args.get(1) {
+            None => 1,
+            Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+            Some(_) => return Err("flatten espera um número inteiro não negativo como profundidade".to_string()),
+        };
+
         match &args[0] {
             Value::List(list) => {
                 let mut flattened = Vec::new();
-                for item in list {
-                    match item {
-                        Value::List(inner_list) => {
-                            flattened.extend(inner_list.clone());
-                        },
-                        other => flattened.push(other.clone()),
-                    }
-                }
+                flatten_into(list, depth, &mut flattened);
                 Ok(Value::List(flattened))
             },
             _ => Err("flatten espera uma lista".to_string()),
         }
     }));
 
-    module.insert("range".to_string(), Value::NativeFunction(|args| {
-        if args.len() < 1 || args.len() > 3 {
-            return Err("range espera 1, 2 ou 3 argumentos".to_string());
+    module.insert("flatten_deep".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("flatten_deep espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::List(list) => {
+                let mut flattened = Vec::new();
+                flatten_into(list, usize::MAX, &mut flattened);
+                Ok(Value::List(flattened))
+            },
+            _ => Err("flatten_deep espera uma lista".to_string()),
         }
+    }));
 
-        let (start, end, step) = match args.len() {
-            1 => {
-                match &args[0] {
-                    Value::Number(n) => (0.0, *n, 1.0),
-                    _ => return Err("range espera números".to_string()),
-                }
-            },
-            2 => {
-                match (&args[0], &args[1]) {
-                    (Value::Number(s), Value::Number(e)) => (*s, *e, 1.0),
-                    _ => return Err("range espera números".to_string()),
-                }
-            },
-            3 => {
-                match (&args[0], &args[1], &args[2]) {
-                    (Value::Number(s), Value::Number(e), Value::Number(st)) => (*s, *e, *st),
-                    _ => return Err("range espera números".to_string()),
-                }
-            },
-            _ => unreachable!(),
+    // Estruturas aninhadas (listas e dicionários, como as vindas de json.parse).
+    module.insert("walk".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("walk espera 2 argumentos".to_string()); }
+        if !is_callable(&args[1]) { return Err("walk espera um valor e uma função".to_string()); }
+        
+        walk(&args[0], &args[1], false)
+    }));
+
+    module.insert("postwalk".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("postwalk espera 2 argumentos".to_string()); }
+        if !is_callable(&args[1]) { return Err("postwalk espera um valor e uma função".to_string()); }
+        
+        walk(&args[0], &args[1], true)
+    }));
+
+    module.insert("deep_equal".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("deep_equal espera 2 argumentos".to_string()); }
+        
+        Ok(Value::Boolean(deep_equal(&args[0], &args[1])))
+    }));
+
+    module.insert("deep_clone".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("deep_clone espera 1 argumento".to_string()); }
+        
+        // Listas, dicionários e objetos nativos copiáveis (conjuntos...) são copiados
+        // por inteiro: alterar a cópia nunca afeta o original.
+        Ok(deep_clone(&args[0]))
+    }));
+
+    module.insert("paths".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("paths espera 1 ou 2 argumentos".to_string()); }
+        
+        let leaves_only = match args.get(1) {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("paths espera um booleano (leaves_only) como segundo argumento".to_string()),
         };
+        let mut paths = Vec::new();
+        collect_paths(&args[0], &mut Vec::new(), leaves_only, &mut paths);
+        Ok(Value::List(paths))
+    }));
 
-        if step == 0.0 {
-            return Err("step não pode ser zero".to_string());
+    // Agrupamento e janelas. Todas aceitam listas, conjuntos, iteradores ou
+    // dicionários (percorridos como pares [chave, valor]). Os dicionários de
+    // group_by, count_by e frequencies trazem as chaves na ordem em que aparecem.
+    module.insert("group_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("group_by espera 2 argumentos".to_string()); }
+        
+        let (items, key_fn) = items_and_function("group_by", &args)?;
+        let mut groups: IndexMap<Value, Value> = IndexMap::new();
+        for item in items {
+            let key = call_function(key_fn, vec![item.clone()])?;
+            match groups.entry(key).or_insert_with(|| Value::List(Vec::new())) {
+                Value::List(group) => group.push(item),
+                _ => unreachable!(),
+            }
         }
+        Ok(Value::Dict(groups))
+    }));
+
+    module.insert("count_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("count_by espera 2 argumentos".to_string()); }
+        
+        let (items, key_fn) = items_and_function("count_by", &args)?;
+        let mut keys = Vec::with_capacity(items.len());
+        for item in items {
+            keys.push(call_function(key_fn, vec![item])?);
+        }
+        Ok(count_values(keys))
+    }));
 
-        let mut result = Vec::new();
-        let mut current = start;
+    module.insert("frequencies".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("frequencies espera 1 argumento".to_string()); }
+        
+        Ok(count_values(collection_items("frequencies", &args[0])?))
+    }));
 
-        if step > 0.0 {
-            while current < end {
-                result.push(Value::Number(current));
-                current += step;
+    module.insert("partition".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("partition espera 2 argumentos".to_string()); }
+        
+        let (items, pred) = items_and_function("partition", &args)?;
+        let mut matching = Vec::new();
+        let mut rest = Vec::new();
+        for item in items {
+            if is_truthy(&call_function(pred, vec![item.clone()])?) {
+                matching.push(item);
+            } else {
+                rest.push(item);
             }
-        } else {
-            while current > end {
-                result.push(Value::Number(current));
-                current += step;
+        }
+        Ok(Value::List(vec![Value::List(matching), Value::List(rest)]))
+    }));
+
+    module.insert("chunk".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("chunk espera 2 argumentos".to_string()); }
+        
+        let items = collection_items("chunk", &args[0])?;
+        let size = positive_count("chunk", &args[1])?;
+        let chunks = items.chunks(size).map(|chunk| Value::List(chunk.to_vec())).collect();
+        Ok(Value::List(chunks))
+    }));
+
+    module.insert("windows".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("windows espera 2 argumentos".to_string()); }
+        
+        let items = collection_items("windows", &args[0])?;
+        let size = positive_count("windows", &args[1])?;
+        let windows = items.windows(size).map(|window| Value::List(window.to_vec())).collect();
+        Ok(Value::List(windows))
+    }));
+
+    module.insert("unzip".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("unzip espera 1 argumento".to_string()); }
+        
+        let rows = collection_items("unzip", &args[0])?;
+        let width = match rows.first() {
+            Some(Value::List(first)) => first.len(),
+            Some(_) => return Err("unzip espera uma lista de listas".to_string()),
+            None => return Ok(Value::List(Vec::new())),
+        };
+        let mut columns = vec![Vec::with_capacity(rows.len()); width];
+        for row in rows {
+            match row {
+                Value::List(row) if row.len() == width => {
+                    for (column, item) in columns.iter_mut().zip(row) {
+                        column.push(item);
+                    }
+                },
+                Value::List(_) => return Err("unzip espera listas do mesmo tamanho".to_string()),
+                _ => return Err("unzip espera uma lista de listas".to_string()),
+            }
+        }
+        Ok(Value::List(columns.into_iter().map(Value::List).collect()))
+    }));
+
+    module.insert("sum".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("sum espera 1 ou 2 argumentos".to_string()); }
+        
+        let items = collection_items("sum", &args[0])?;
+        let key_fn = match args.get(1) {
+            None => None,
+            Some(func) if is_callable(func) => Some(func),
+            Some(_) => return Err("sum espera uma função como segundo argumento".to_string()),
+        };
+        let mut total = 0.0;
+        for item in items {
+            let value = match key_fn {
+                Some(func) => call_function(func, vec![item])?,
+                None => item,
+            };
+            match value {
+                Value::Number(n) => total += n,
+                _ => return Err("sum espera números".to_string()),
             }
         }
+        Ok(Value::Number(total))
+    }));
 
-        Ok(Value::List(result))
+    module.insert("min_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("min_by espera 2 argumentos".to_string()); }
+        
+        let (items, key_fn) = items_and_function("min_by", &args)?;
+        extreme_by(items, key_fn, Ordering::Less)
     }));
 
-    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
-    Value::Dict(dict_map)
+    module.insert("max_by".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("max_by espera 2 argumentos".to_string()); }
+        
+        let (items, key_fn) = items_and_function("max_by", &args)?;
+        extreme_by(items, key_fn, Ordering::Greater)
+    }));
+
+    // Estruturas mutáveis: deques e heaps são referências (como objetos), então
+    // push/pop alteram a estrutura no lugar em O(1)/O(log n) sem copiar a lista.
+    module.insert("deque".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 1 { return Err("deque espera no máximo 1 argumento".to_string()); }
+        
+        let items = match args.first() {
+            None => VecDeque::new(),
+            Some(source) => collection_items("deque", source)?.into(),
+        };
+        Ok(Value::Handle(Handle::new("deque", items)))
+    }));
+
+    module.insert("push_back".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("push_back espera 2 argumentos".to_string()); }
+        
+        with_deque("push_back", &args[0], |deque| deque.push_back(args[1].clone()))?;
+        Ok(Value::Nil)
+    }));
+
+    module.insert("push_front".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("push_front espera 2 argumentos".to_string()); }
+        
+        with_deque("push_front", &args[0], |deque| deque.push_front(args[1].clone()))?;
+        Ok(Value::Nil)
+    }));
+
+    module.insert("pop_back".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("pop_back espera 1 argumento".to_string()); }
+        
+        Ok(with_deque("pop_back", &args[0], |deque| deque.pop_back())?.unwrap_or(Value::Nil))
+    }));
+
+    module.insert("pop_front".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("pop_front espera 1 argumento".to_string()); }
+        
+        Ok(with_deque("pop_front", &args[0], |deque| deque.pop_front())?.unwrap_or(Value::Nil))
+    }));
+
+    module.insert("peek_back".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("peek_back espera 1 argumento".to_string()); }
+        
+        Ok(with_deque("peek_back", &args[0], |deque| deque.back().cloned())?.unwrap_or(Value::Nil))
+    }));
+
+    module.insert("peek_front".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("peek_front espera 1 argumento".to_string()); }
+        
+        Ok(with_deque("peek_front", &args[0], |deque| deque.front().cloned())?.unwrap_or(Value::Nil))
+    }));
+
+    module.insert("heap".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 2 { return Err("heap espera no máximo 2 argumentos".to_string()); }
+        
+        // heap(), heap(itens), heap(opções) ou heap(itens, opções), com
+        // opções {"key": função, "max": bool}. Por padrão o menor sai primeiro.
+        let (source, options) = match args.as_slice() {
+            [] => (None, None),
+            [Value::Dict(options)] if object_kind(&args[0]).is_none() => (None, Some(options)),
+            [source] => (Some(source), None),
+            [source, Value::Dict(options)] => (Some(source), Some(options)),
+            _ => return Err("heap espera uma coleção e/ou um dicionário de opções".to_string()),
+        };
+        let key_fn = match options.and_then(|o| o.get(&Value::String("key".to_string()))) {
+            None | Some(Value::Nil) => None,
+            Some(func) if is_callable(func) => Some(func.clone()),
+            Some(_) => return Err("heap espera uma função na opção 'key'".to_string()),
+        };
+        let max = match options.and_then(|o| o.get(&Value::String("max".to_string()))) {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err("heap espera um booleano na opção 'max'".to_string()),
+        };
+
+        let mut state = HeapState { heap: BinaryHeap::new(), key_fn, max, next_seq: 0 };
+        if let Some(source) = source {
+            for item in collection_items("heap", source)? {
+                state.push(item)?;
+            }
+        }
+        Ok(Value::Handle(Handle::new("heap", state)))
+    }));
+
+    module.insert("heap_push".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("heap_push espera 2 argumentos".to_string()); }
+        
+        // A função de chave é chamada antes de pegar o heap emprestado, pois ela
+        // pode ser uma função Snask que usa o mesmo heap.
+        let (key_fn, max) = with_heap("heap_push", &args[0], |state| (state.key_fn.clone(), state.max))?;
+        let key = match &key_fn {
+            Some(func) => call_function(func, vec![args[1].clone()])?,
+            None => args[1].clone(),
+        };
+        with_heap("heap_push", &args[0], |state| {
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.heap.push(HeapEntry { key, seq, item: args[1].clone(), max });
+        })?;
+        Ok(Value::Nil)
+    }));
+
+    module.insert("heap_pop".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("heap_pop espera 1 argumento".to_string()); }
+        
+        Ok(with_heap("heap_pop", &args[0], |state| state.heap.pop())?.map(|entry| entry.item).unwrap_or(Value::Nil))
+    }));
+
+    module.insert("heap_peek".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("heap_peek espera 1 argumento".to_string()); }
+        
+        Ok(with_heap("heap_peek", &args[0], |state| state.heap.peek().map(|entry| entry.item.clone()))?.unwrap_or(Value::Nil))
+    }));
+
+    module.insert("nlargest".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("nlargest espera 2 ou 3 argumentos".to_string()); }
+        
+        select_extremes("nlargest", &args, true)
+    }));
+
+    module.insert("nsmallest".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("nsmallest espera 2 ou 3 argumentos".to_string()); }
+        
+        select_extremes("nsmallest", &args, false)
+    }));
+
+    module.insert("release".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("release espera 1 argumento".to_string()); }
+        
+        // Libera já a memória de um deque ou heap; sem release ela é liberada
+        // quando a última referência deixa de existir.
+        match &args[0] {
+            Value::Handle(handle) if matches!(handle.kind(), "deque" | "heap") => {
+                Ok(Value::Boolean(handle.release("release")?))
+            },
+            _ => Err("release espera um deque ou heap".to_string()),
+        }
+    }));
+
+    module.insert("range".to_string(), Value::NativeFunction(|args| {
+        Ok(parse_range_args(&args)?.to_object())
+    }));
+
+    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
+    Value::Dict(dict_map)
+}
+
+/// Lê os argumentos `(end)`, `(start, end)` ou `(start, end, step)` usados por `range`,
+/// seguidos opcionalmente de um dicionário de opções `{"inclusive": bool}`.
+fn parse_range_args(args: &[Value]) -> Result<RangeSpec, String> {
+    let (args, options) = match args.split_last() {
+        Some((Value::Dict(options), rest)) => (rest, Some(options)),
+        _ => (args, None),
+    };
+    if args.is_empty() || args.len() > 3 {
+        return Err("range espera 1, 2 ou 3 argumentos".to_string());
+    }
+
+    let (start, end, step) = match args {
+        [Value::Number(e)] => (0.0, *e, 1.0),
+        [Value::Number(s), Value::Number(e)] => (*s, *e, 1.0),
+        [Value::Number(s), Value::Number(e), Value::Number(st)] => (*s, *e, *st),
+        _ => return Err("range espera números".to_string()),
+    };
+
+    if !start.is_finite() || !end.is_finite() || !step.is_finite() {
+        return Err("range espera números finitos (sem NaN ou infinito)".to_string());
+    }
+    if step == 0.0 {
+        return Err("step não pode ser zero".to_string());
+    }
+
+    let inclusive = match options.and_then(|o| o.get(&Value::String("inclusive".to_string()))) {
+        None | Some(Value::Nil) => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(_) => return Err("range espera um booleano na opção 'inclusive'".to_string()),
+    };
+
+    Ok(RangeSpec { start, end: Some(end), step, inclusive })
+}
+
+/// Sequência aritmética `start + i*step`, calculada pelo índice sem materializar a lista.
+struct RangeSpec {
+    start: f64,
+    /// `None` para sequências infinitas (`count`).
+    end: Option<f64>,
+    step: f64,
+    inclusive: bool,
+}
+
+impl RangeSpec {
+    /// Maior valor até onde a aritmética em f64 com inteiros é exata.
+    const MAX_EXACT: f64 = 9007199254740992.0;
+
+    fn to_object(&self) -> Value {
+        new_iterator("range", vec![
+            ("start", Value::Number(self.start)),
+            ("end", self.end.map(Value::Number).unwrap_or(Value::Nil)),
+            ("step", Value::Number(self.step)),
+            ("inclusive", Value::Boolean(self.inclusive)),
+        ])
+    }
+
+    fn from_object(value: &Value) -> Result<Self, String> {
+        let number = |name: &str| match object_field(value, name) {
+            Some(Value::Number(n)) => Ok(*n),
+            _ => Err(format!("range inválido: campo '{}' ausente", name)),
+        };
+        Ok(RangeSpec {
+            start: number("start")?,
+            end: number("end").ok(),
+            step: number("step")?,
+            inclusive: matches!(object_field(value, "inclusive"), Some(Value::Boolean(true))),
+        })
+    }
+
+    /// Início e passo inteiros: cada elemento é exato e o tamanho sai de divisão inteira.
+    fn is_integral(&self) -> bool {
+        self.start.fract() == 0.0 && self.step.fract() == 0.0
+            && self.start.abs() < Self::MAX_EXACT && self.step.abs() < Self::MAX_EXACT
+    }
+
+    /// Quantidade de elementos; `None` se a sequência for infinita.
+    fn len(&self) -> Option<u64> {
+        let end = self.end?;
+        if self.is_integral() {
+            return Some(self.integral_len(end));
+        }
+
+        let span = (end - self.start) / self.step;
+        if span >= Self::MAX_EXACT {
+            return Some(span.floor() as u64 + 1);
+        }
+        // Parte da estimativa pela divisão e corrige olhando os próprios elementos.
+        let mut len = if span > 0.0 { span.floor() as u64 + 1 } else { 0 };
+        while len > 0 && !self.before_end(len - 1, end) {
+            len -= 1;
+        }
+        while self.before_end(len, end) {
+            len += 1;
+        }
+        Some(len)
+    }
+
+    /// Tamanho exato com início e passo inteiros, sem arredondar um `end` fracionário.
+    fn integral_len(&self, end: f64) -> u64 {
+        let (start, step) = (self.start as i128, self.step as i128);
+        // Conversões `as` de f64 para i128 saturam, então `end` enorme não estoura.
+        let count = if step > 0 {
+            let last = if self.inclusive { end.floor() as i128 } else { end.ceil() as i128 - 1 };
+            if last < start { 0 } else { (last - start) / step + 1 }
+        } else {
+            let last = if self.inclusive { end.ceil() as i128 } else { end.floor() as i128 + 1 };
+            if last > start { 0 } else { (start - last) / -step + 1 }
+        };
+        u64::try_from(count).unwrap_or(u64::MAX)
+    }
+
+    /// Se o elemento `index` entra num range que termina em `end`.
+    fn before_end(&self, index: u64, end: f64) -> bool {
+        if self.matches(index, end) {
+            return self.inclusive;
+        }
+        let value = self.get(index);
+        if self.step > 0.0 { value < end } else { value > end }
+    }
+
+    /// Se o elemento `index` é `value`, aceitando alguns ULPs de erro com passo fracionário.
+    fn matches(&self, index: u64, value: f64) -> bool {
+        let element = self.get(index);
+        if self.is_integral() {
+            return element == value;
+        }
+        let magnitude = value.abs().max(self.start.abs() + (index as f64 * self.step).abs());
+        (element - value).abs() <= 4.0 * f64::EPSILON * magnitude
+    }
+
+    fn get(&self, index: u64) -> f64 {
+        if self.is_integral() && (index as f64) * self.step.abs() < Self::MAX_EXACT {
+            (self.start as i64 + index as i64 * self.step as i64) as f64
+        } else {
+            self.start + index as f64 * self.step
+        }
+    }
+
+    fn contains(&self, value: f64) -> bool {
+        let index = ((value - self.start) / self.step).round();
+        if index.is_nan() || index < 0.0 || index >= u64::MAX as f64 {
+            return false;
+        }
+        let in_bounds = match self.len() {
+            Some(len) => index < len as f64,
+            None => true,
+        };
+        in_bounds && self.matches(index as u64, value)
+    }
+}
+
+fn is_range(value: &Value) -> bool {
+    is_iterator(value) && matches!(object_field(value, "op"), Some(Value::String(op)) if op == "range")
+}
+
+/// Chave usada para marcar dicionários que representam objetos nativos
+/// (iteradores, conjuntos, handles...), já que `Value` não tem variantes próprias para eles.
+pub const TYPE_KEY: &str = "__type";
+
+/// Cria um objeto nativo: um `Value::Dict` marcado com `TYPE_KEY = kind`.
+pub fn new_object(kind: &str, fields: Vec<(&str, Value)>) -> Value {
+    let mut map = IndexMap::new();
+    map.insert(Value::String(TYPE_KEY.to_string()), Value::String(kind.to_string()));
+    for (key, value) in fields {
+        map.insert(Value::String(key.to_string()), value);
+    }
+    Value::Dict(map)
+}
+
+/// Retorna o tipo de um objeto nativo criado com `new_object` ou guardado num `Handle`.
+pub fn object_kind(value: &Value) -> Option<&str> {
+    match value {
+        Value::Handle(handle) => Some(handle.kind()),
+        _ => match object_field(value, TYPE_KEY) {
+            Some(Value::String(kind)) => Some(kind.as_str()),
+            _ => None,
+        },
+    }
+}
+
+/// Lê um campo de um objeto nativo (ou de qualquer dicionário com chaves string).
+pub fn object_field<'a>(value: &'a Value, field: &str) -> Option<&'a Value> {
+    match value {
+        Value::Dict(map) => map.get(&Value::String(field.to_string())),
+        _ => None,
+    }
+}
+
+/// Estado mutável de um objeto nativo guardado em `Value::Handle`, compartilhado entre cópias.
+pub trait NativeObject: Any {
+    /// Texto mostrado pelo Display de `Value`.
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Cópia independente para `deep_clone`; `None` se o recurso não pode ser duplicado.
+    fn duplicate(&self) -> Option<Box<dyn NativeObject>> {
+        None
+    }
+
+    /// Igualdade por conteúdo; por padrão um objeto só é igual a si mesmo.
+    fn content_eq(&self, _other: &dyn NativeObject) -> bool {
+        false
+    }
+}
+
+/// Referência a um `NativeObject`, carregada por `Value::Handle`.
+#[derive(Clone)]
+pub struct Handle {
+    kind: &'static str,
+    object: Rc<RefCell<Box<dyn NativeObject>>>,
+}
+
+impl Handle {
+    pub fn new(kind: &'static str, object: impl NativeObject) -> Self {
+        Self::from_box(kind, Box::new(object))
+    }
+
+    fn from_box(kind: &'static str, object: Box<dyn NativeObject>) -> Self {
+        Handle { kind, object: Rc::new(RefCell::new(object)) }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// Dá acesso ao estado concreto; falha se o tipo não bater, se já foi liberado ou se está em uso.
+    pub fn with<T: NativeObject, R>(&self, name: &str, f: impl FnOnce(&mut T) -> R) -> Result<R, String> {
+        let mut object = self.object.try_borrow_mut()
+            .map_err(|_| format!("{}: o {} já está em uso", name, self.kind))?;
+        let object: &mut dyn Any = &mut **object;
+        if object.is::<Released>() {
+            return Err(format!("{}: {} já foi liberado", name, self.kind));
+        }
+        object.downcast_mut::<T>()
+            .map(f)
+            .ok_or_else(|| format!("{}: {} inválido", name, self.kind))
+    }
+
+    /// Descarta o estado agora (o `Drop` fecha o recurso); `false` se já tinha sido liberado.
+    pub fn release(&self, name: &str) -> Result<bool, String> {
+        let released = {
+            let mut object = self.object.try_borrow_mut()
+                .map_err(|_| format!("{}: o {} já está em uso", name, self.kind))?;
+            let current: &dyn Any = &**object;
+            if current.is::<Released>() {
+                return Ok(false);
+            }
+            std::mem::replace(&mut *object, Box::new(Released))
+        };
+        drop(released);
+        Ok(true)
+    }
+
+    /// Referência que não mantém o objeto vivo (ex.: para fechar recursos ao sair).
+    pub fn downgrade(&self) -> WeakHandle {
+        WeakHandle { kind: self.kind, object: Rc::downgrade(&self.object) }
+    }
+
+    /// Cópia independente (ou o próprio objeto, se ele não puder ser copiado).
+    pub fn deep_clone(&self) -> Handle {
+        match self.object.try_borrow().ok().and_then(|object| object.duplicate()) {
+            Some(copy) => Self::from_box(self.kind, copy),
+            None => self.clone(),
+        }
+    }
+}
+
+impl PartialEq for Handle {
+    fn eq(&self, other: &Self) -> bool {
+        if Rc::ptr_eq(&self.object, &other.object) {
+            return true;
+        }
+        match (self.object.try_borrow(), other.object.try_borrow()) {
+            (Ok(a), Ok(b)) => self.kind == other.kind && a.content_eq(&**b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Handle {}
+
+// O hash usa só o tipo, que nunca muda: alterar o objeto não invalida chaves de HashMap.
+impl Hash for Handle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.object.try_borrow() {
+            Ok(object) if (&**object as &dyn Any).is::<Released>() => write!(f, "<{} liberado>", self.kind),
+            Ok(object) => object.describe(f),
+            Err(_) => write!(f, "<{}>", self.kind),
+        }
+    }
+}
+
+/// Referência fraca criada por `Handle::downgrade`.
+pub struct WeakHandle {
+    kind: &'static str,
+    object: Weak<RefCell<Box<dyn NativeObject>>>,
+}
+
+impl WeakHandle {
+    /// O handle, se o objeto ainda existir.
+    pub fn upgrade(&self) -> Option<Handle> {
+        self.object.upgrade().map(|object| Handle { kind: self.kind, object })
+    }
+}
+
+/// O que fica no lugar de um objeto depois de `Handle::release`.
+struct Released;
+
+impl NativeObject for Released {
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<liberado>")
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Escreve `prefix(a, b, c)` usando o Display de cada item.
+fn describe_items<'a>(f: &mut fmt::Formatter, prefix: &str, items: impl Iterator<Item = &'a Value>) -> fmt::Result {
+    write!(f, "{}(", prefix)?;
+    for (i, item) in items.enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    write!(f, ")")
+}
+
+/// Iterador Rust por trás de um iterador Snask; cada item pode falhar
+/// porque os adaptadores chamam funções do usuário.
+pub type ValueIter = Box<dyn Iterator<Item = Result<Value, String>>>;
+
+fn new_iterator(op: &str, mut fields: Vec<(&str, Value)>) -> Value {
+    fields.push(("op", Value::String(op.to_string())));
+    new_object("iterator", fields)
+}
+
+/// Iterador cujos itens são produzidos por uma função nativa: `next([state])`
+/// retorna `[item]` para o próximo item ou `nil` quando acabar. Permite que
+/// outros módulos (ex.: `io.lines`) exponham fontes preguiçosas.
+pub fn new_generator(next: fn(Vec<Value>) -> Result<Value, String>, state: Value) -> Value {
+    new_iterator("generator", vec![("next", Value::NativeFunction(next)), ("state", state)])
+}
+
+pub fn is_iterator(value: &Value) -> bool {
+    object_kind(value) == Some("iterator")
+}
+
+/// Listas, conjuntos, iteradores e dicionários (como lista de pares
+/// `[chave, valor]`) podem ser percorridos com `open_iter`.
+pub fn is_iterable(value: &Value) -> bool {
+    match value {
+        Value::List(_) | Value::Dict(_) => true,
+        Value::Handle(handle) => matches!(handle.kind(), "set" | "deque" | "heap"),
+        _ => false,
+    }
+}
+
+/// Resultado de um adaptador: continua preguiçoso se alguma entrada for um
+/// iterador, ou é materializado se todas as entradas forem listas.
+fn adapt(inputs: &[Value], iterator: Value) -> Result<Value, String> {
+    if inputs.iter().any(is_iterator) {
+        Ok(iterator)
+    } else {
+        Ok(Value::List(open_iter(&iterator)?.collect::<Result<Vec<_>, _>>()?))
+    }
+}
+
+fn collect_iterator(args: Vec<Value>) -> Result<Value, String> {
+    if args.len() != 1 { return Err("collect espera 1 argumento".to_string()); }
+    
+    match &args[0] {
+        source if is_iterable(source) => {
+            Ok(Value::List(open_iter(source)?.collect::<Result<Vec<_>, _>>()?))
+        },
+        _ => Err("collect espera uma lista ou iterador".to_string()),
+    }
+}
+
+/// Abre uma lista ou iterador Snask como um iterador Rust preguiçoso.
+pub fn open_iter(value: &Value) -> Result<ValueIter, String> {
+    if let Value::List(list) = value {
+        return Ok(Box::new(list.clone().into_iter().map(Ok)));
+    }
+    if is_set(value) {
+        return Ok(Box::new(set_items("iter", value)?.into_iter().map(Ok)));
+    }
+    // Deques e heaps são percorridos a partir de uma cópia do conteúdo atual;
+    // o heap na ordem em que os itens sairiam com heap_pop.
+    match object_kind(value) {
+        Some("deque") => {
+            let items: Vec<Value> = with_deque("iter", value, |deque| deque.iter().cloned().collect())?;
+            return Ok(Box::new(items.into_iter().map(Ok)));
+        },
+        Some("heap") => {
+            let items = with_heap("iter", value, |state| state.sorted_items())?;
+            return Ok(Box::new(items.into_iter().map(Ok)));
+        },
+        _ => {},
+    }
+    if !is_iterator(value) {
+        return match value {
+            Value::Dict(map) => {
+                let items: Vec<Value> = map.iter()
+                    .map(|(k, v)| Value::List(vec![k.clone(), v.clone()]))
+                    .collect();
+                Ok(Box::new(items.into_iter().map(Ok)))
+            },
+            _ => Err("valor não é uma lista nem um iterador".to_string()),
+        };
+    }
+
+    let field = |name: &str| object_field(value, name).cloned().unwrap_or(Value::Nil);
+    let number = |name: &str| match field(name) {
+        Value::Number(n) => Ok(n),
+        _ => Err(format!("iterador inválido: campo '{}' ausente", name)),
+    };
+    let op = match field("op") {
+        Value::String(op) => op,
+        _ => return Err("iterador inválido: campo 'op' ausente".to_string()),
+    };
+
+    let iter: ValueIter = match op.as_str() {
+        "list" => match field("items") {
+            Value::List(items) => Box::new(items.into_iter().map(Ok)),
+            _ => return Err("iterador inválido: campo 'items' ausente".to_string()),
+        },
+        "range" => {
+            let range = RangeSpec::from_object(value)?;
+            match range.len() {
+                Some(len) => Box::new((0..len).map(move |i| Ok(Value::Number(range.get(i))))),
+                None => Box::new((0u64..).map(move |i| Ok(Value::Number(range.get(i))))),
+            }
+        },
+        "generator" => {
+            let (next, state) = (field("next"), field("state"));
+            let mut finished = false;
+            Box::new(std::iter::from_fn(move || {
+                if finished {
+                    return None;
+                }
+                match call_function(&next, vec![state.clone()]) {
+                    Ok(Value::List(mut item)) if item.len() == 1 => item.pop().map(Ok),
+                    Ok(Value::Nil) => {
+                        finished = true;
+                        None
+                    },
+                    Ok(_) => {
+                        finished = true;
+                        Some(Err("iterador inválido: 'next' deve retornar [item] ou nil".to_string()))
+                    },
+                    Err(e) => {
+                        finished = true;
+                        Some(Err(e))
+                    },
+                }
+            }))
+        },
+        "map" => {
+            let func = field("func");
+            Box::new(open_iter(&field("source"))?.map(move |item| {
+                item.and_then(|item| call_function(&func, vec![item]))
+            }))
+        },
+        "filter" => {
+            let func = field("func");
+            Box::new(open_iter(&field("source"))?.filter_map(move |item| {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(e) => return Some(Err(e)),
+                };
+                match call_function(&func, vec![item.clone()]) {
+                    Ok(keep) if is_truthy(&keep) => Some(Ok(item)),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            }))
+        },
+        "take_while" => {
+            let func = field("func");
+            Box::new(open_iter(&field("source"))?.map_while(move |item| {
+                let item = match item {
+                    Ok(item) => item,
+                    Err(e) => return Some(Err(e)),
+                };
+                match call_function(&func, vec![item.clone()]) {
+                    Ok(keep) if is_truthy(&keep) => Some(Ok(item)),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                }
+            }))
+        },
+        "take" => Box::new(open_iter(&field("source"))?.take(number("n")? as usize)),
+        "skip" => {
+            let n = number("n")? as usize;
+            // Erros de itens pulados não podem ser engolidos.
+            Box::new(open_iter(&field("source"))?
+                .enumerate()
+                .filter(move |(i, item)| *i >= n || item.is_err())
+                .map(|(_, item)| item))
+        },
+        "enumerate" => {
+            Box::new(open_iter(&field("source"))?.enumerate().map(|(i, item)| {
+                item.map(|item| Value::List(vec![Value::Number(i as f64), item]))
+            }))
+        },
+        "zip" | "chain" => {
+            let sources = match field("sources") {
+                Value::List(sources) => sources,
+                _ => return Err("iterador inválido: campo 'sources' ausente".to_string()),
+            };
+            let mut iters = sources.iter().map(open_iter).collect::<Result<Vec<_>, _>>()?;
+            if op == "chain" {
+                Box::new(iters.into_iter().flatten())
+            } else {
+                Box::new(std::iter::from_fn(move || {
+                    let mut row = Vec::with_capacity(iters.len());
+                    for iter in iters.iter_mut() {
+                        match iter.next()? {
+                            Ok(item) => row.push(item),
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                    Some(Ok(Value::List(row)))
+                }))
+            }
+        },
+        other => return Err(format!("iterador inválido: operação desconhecida '{}'", other)),
+    };
+
+    Ok(iter)
+}
+
+/// Lê o argumento de chave de `sort`: `nil`, uma função, ou uma lista de chaves
+/// onde cada uma é uma função ou um dicionário `{"key": função, "descending": bool}`.
+fn parse_sort_keys(spec: &Value) -> Result<Vec<(Value, bool)>, String> {
+    let parse_one = |key: &Value| match key {
+        key if is_callable(key) => Ok((key.clone(), false)),
+        Value::Dict(_) => {
+            let key_fn = match object_field(key, "key") {
+                Some(key_fn) if is_callable(key_fn) => key_fn.clone(),
+                _ => return Err("sort espera uma função no campo 'key'".to_string()),
+            };
+            let descending = match object_field(key, "descending") {
+                None | Some(Value::Nil) => false,
+                Some(Value::Boolean(b)) => *b,
+                Some(_) => return Err("sort espera um booleano no campo 'descending'".to_string()),
+            };
+            Ok((key_fn, descending))
+        },
+        _ => Err("sort espera uma função ou uma lista de funções como chave".to_string()),
+    };
+
+    match spec {
+        Value::Nil => Ok(Vec::new()),
+        Value::List(keys) => keys.iter().map(parse_one).collect(),
+        key => Ok(vec![parse_one(key)?]),
+    }
+}
+
+/// Posição de cada variante na ordem total entre valores de tipos diferentes.
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Nil => 0,
+        Value::Boolean(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::List(_) => 4,
+        Value::Dict(_) => 5,
+        _ => 6,
+    }
+}
+
+/// Ordem total usada por `sort` e `compare`: nil < booleanos < números (NaN por último) < strings < listas < dicionários < funções.
+pub fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Boolean(x), Value::Boolean(y)) => x.cmp(y),
+        (Value::Number(x), Value::Number(y)) => {
+            // Só falha com NaN: nesse caso NaN fica depois de qualquer outro número.
+            x.partial_cmp(y).unwrap_or_else(|| x.is_nan().cmp(&y.is_nan()))
+        },
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::List(x), Value::List(y)) => {
+            x.iter()
+                .zip(y)
+                .map(|(ex, ey)| compare_values(ex, ey))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or_else(|| x.len().cmp(&y.len()))
+        },
+        (Value::Dict(x), Value::Dict(y)) => {
+            // A ordem de inserção não entra na comparação.
+            fn sorted_entries(map: &IndexMap<Value, Value>) -> Vec<(&Value, &Value)> {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|(ka, _), (kb, _)| compare_values(ka, kb));
+                entries
+            }
+            let (ex, ey) = (sorted_entries(x), sorted_entries(y));
+            ex.iter()
+                .zip(&ey)
+                .map(|((kx, vx), (ky, vy))| compare_values(kx, ky).then_with(|| compare_values(vx, vy)))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or_else(|| ex.len().cmp(&ey.len()))
+        },
+        _ => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+fn deep_clone(value: &Value) -> Value {
+    match value {
+        Value::List(list) => Value::List(list.iter().map(deep_clone).collect()),
+        Value::Dict(map) => Value::Dict(map.iter().map(|(k, v)| (deep_clone(k), deep_clone(v))).collect()),
+        Value::Handle(handle) => Value::Handle(handle.deep_clone()),
+        other => other.clone(),
+    }
+}
+
+/// Merge sort estável que para no primeiro erro e tolera comparadores inconsistentes.
+fn try_merge_sort<T>(
+    mut items: Vec<T>,
+    compare: &mut impl FnMut(&T, &T) -> Result<Ordering, String>,
+) -> Result<Vec<T>, String> {
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = try_merge_sort(items, compare)?;
+    let right = try_merge_sort(right, compare)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let (mut left, mut right) = (left.into_iter().peekable(), right.into_iter().peekable());
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Só `Greater` tira da direita: empates mantêm a ordem original.
+        let next = if compare(a, b)? == Ordering::Greater { right.next() } else { left.next() };
+        merged.extend(next);
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// Remove repetições mantendo a primeira ocorrência de cada valor, em O(n).
+fn unique_values(items: impl IntoIterator<Item = Value>) -> Vec<Value> {
+    let mut seen = HashSet::new();
+    items.into_iter().filter(|item| seen.insert(item.clone())).collect()
+}
+
+/// Conjunto na ordem de inserção: `index` leva cada valor à sua posição em `slots`.
+#[derive(Clone, Default)]
+struct SetObject {
+    slots: Vec<Option<Value>>,
+    index: HashMap<Value, usize>,
+}
+
+impl SetObject {
+    fn insert(&mut self, item: Value) -> bool {
+        if self.index.contains_key(&item) {
+            return false;
+        }
+        self.index.insert(item.clone(), self.slots.len());
+        self.slots.push(Some(item));
+        true
+    }
+
+    fn remove(&mut self, item: &Value) -> bool {
+        let Some(slot) = self.index.remove(item) else { return false };
+        self.slots[slot] = None;
+        // Remoções deixam buracos; compacta quando eles passam da metade.
+        if self.slots.len() > 2 * self.index.len() + 8 {
+            let items: Vec<Value> = self.slots.drain(..).flatten().collect();
+            self.index.clear();
+            for item in items {
+                self.insert(item);
+            }
+        }
+        true
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Value> {
+        self.slots.iter().flatten()
+    }
+}
+
+impl NativeObject for SetObject {
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        describe_items(f, "set", self.iter())
+    }
+
+    fn duplicate(&self) -> Option<Box<dyn NativeObject>> {
+        Some(Box::new(self.clone()))
+    }
+
+    /// Conjuntos são iguais se tiverem os mesmos elementos, em qualquer ordem.
+    fn content_eq(&self, other: &dyn NativeObject) -> bool {
+        let other: &dyn Any = other;
+        other.downcast_ref::<SetObject>().is_some_and(|other| {
+            self.index.len() == other.index.len() && self.index.keys().all(|item| other.index.contains_key(item))
+        })
+    }
+}
+
+fn new_set(items: Vec<Value>) -> Value {
+    let mut set = SetObject::default();
+    for item in items {
+        set.insert(item);
+    }
+    Value::Handle(Handle::new("set", set))
+}
+
+pub fn is_set(value: &Value) -> bool {
+    object_kind(value) == Some("set")
+}
+
+fn with_set<R>(name: &str, value: &Value, f: impl FnOnce(&mut SetObject) -> R) -> Result<R, String> {
+    match value {
+        Value::Handle(handle) if handle.kind() == "set" => handle.with(name, f),
+        _ => Err(format!("{} espera um conjunto", name)),
+    }
+}
+
+/// Elementos de um conjunto ou de uma lista usada como conjunto.
+fn set_items(name: &str, value: &Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::List(list) => Ok(list.clone()),
+        set if is_set(set) => with_set(name, set, |set| set.iter().cloned().collect()),
+        _ => Err(format!("{} espera listas ou conjuntos", name)),
+    }
+}
+
+fn set_result(inputs: &[Value], items: Vec<Value>) -> Value {
+    if inputs.iter().any(is_set) {
+        new_set(items)
+    } else {
+        Value::List(items)
+    }
+}
+
+/// Elementos de uma lista, conjunto, iterador ou dicionário (como pares).
+fn collection_items(name: &str, value: &Value) -> Result<Vec<Value>, String> {
+    if !is_iterable(value) {
+        return Err(format!("{} espera uma lista, conjunto, dicionário ou iterador", name));
+    }
+    open_iter(value)?.collect()
+}
+
+/// Valida os argumentos `(coleção, função)` comuns às funções de agrupamento.
+fn items_and_function<'a>(name: &str, args: &'a [Value]) -> Result<(Vec<Value>, &'a Value), String> {
+    if !is_iterable(&args[0]) || !is_callable(&args[1]) {
+        return Err(format!("{} espera uma coleção e uma função", name));
+    }
+    Ok((collection_items(name, &args[0])?, &args[1]))
+}
+
+/// Valida um tamanho inteiro positivo (usado por `chunk` e `windows`).
+fn positive_count(name: &str, value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Number(n) if *n >= 1.0 && n.fract() == 0.0 => Ok(*n as usize),
+        _ => Err(format!("{} espera um número inteiro positivo como tamanho", name)),
+    }
+}
+
+/// Conta as ocorrências de cada valor, com as chaves na ordem da primeira aparição.
+fn count_values(values: Vec<Value>) -> Value {
+    let mut counts: IndexMap<Value, f64> = IndexMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0.0) += 1.0;
+    }
+    Value::Dict(counts.into_iter().map(|(k, count)| (k, Value::Number(count))).collect())
+}
+
+/// Elemento com a menor (`Less`) ou maior (`Greater`) chave segundo `compare_values`.
+/// Em caso de empate fica o primeiro; coleção vazia retorna `nil`.
+fn extreme_by(items: Vec<Value>, key_fn: &Value, wanted: Ordering) -> Result<Value, String> {
+    let mut best: Option<(Value, Value)> = None;
+    for item in items {
+        let key = call_function(key_fn, vec![item.clone()])?;
+        let replace = match &best {
+            Some((best_key, _)) => compare_values(&key, best_key) == wanted,
+            None => true,
+        };
+        if replace {
+            best = Some((key, item));
+        }
+    }
+    Ok(best.map(|(_, item)| item).unwrap_or(Value::Nil))
+}
+
+/// Remove até `depth` níveis de listas aninhadas.
+fn flatten_into(list: &[Value], depth: usize, out: &mut Vec<Value>) {
+    for item in list {
+        match item {
+            Value::List(inner_list) if depth > 0 => flatten_into(inner_list, depth - 1, out),
+            other => out.push(other.clone()),
+        }
+    }
+}
+
+/// Aplica `func` a todos os nós, em pré-ordem (`walk`) ou pós-ordem (`postwalk`).
+fn walk(value: &Value, func: &Value, post_order: bool) -> Result<Value, String> {
+    let node = if post_order { value.clone() } else { call_function(func, vec![value.clone()])? };
+
+    let rebuilt = match &node {
+        Value::List(list) => {
+            let mut items = Vec::with_capacity(list.len());
+            for item in list {
+                items.push(walk(item, func, post_order)?);
+            }
+            Value::List(items)
+        },
+        Value::Dict(map) => {
+            let mut rebuilt = IndexMap::with_capacity(map.len());
+            for (key, child) in map {
+                rebuilt.insert(key.clone(), walk(child, func, post_order)?);
+            }
+            Value::Dict(rebuilt)
+        },
+        other => other.clone(),
+    };
+
+    if post_order { call_function(func, vec![rebuilt]) } else { Ok(rebuilt) }
+}
+
+/// Igualdade estrutural: a ordem das chaves de dicionários não importa e NaN é igual a NaN.
+fn deep_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y || (x.is_nan() && y.is_nan()),
+        (Value::List(x), Value::List(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(ex, ey)| deep_equal(ex, ey))
+        },
+        (Value::Dict(x), Value::Dict(y)) => {
+            x.len() == y.len()
+                && x.iter().all(|(k, vx)| y.get(k).is_some_and(|vy| deep_equal(vx, vy)))
+        },
+        _ => a == b,
+    }
+}
+
+/// Acumula o caminho (lista de chaves/índices) de cada nó abaixo da raiz, em pré-ordem.
+fn collect_paths(value: &Value, prefix: &mut Vec<Value>, leaves_only: bool, out: &mut Vec<Value>) {
+    let children: Vec<(Value, &Value)> = match value {
+        Value::List(list) => list.iter().enumerate().map(|(i, item)| (Value::Number(i as f64), item)).collect(),
+        Value::Dict(map) => map.iter().map(|(k, child)| (k.clone(), child)).collect(),
+        _ => Vec::new(),
+    };
+
+    for (key, child) in children {
+        prefix.push(key);
+        let is_leaf = !matches!(child, Value::List(_) | Value::Dict(_));
+        if is_leaf || !leaves_only {
+            out.push(Value::List(prefix.clone()));
+        }
+        collect_paths(child, prefix, leaves_only, out);
+        prefix.pop();
+    }
+}
+
+fn with_deque<R>(name: &str, value: &Value, f: impl FnOnce(&mut VecDeque<Value>) -> R) -> Result<R, String> {
+    match value {
+        Value::Handle(handle) if handle.kind() == "deque" => handle.with(name, f),
+        _ => Err(format!("{} espera um deque", name)),
+    }
+}
+
+fn with_heap<R>(name: &str, value: &Value, f: impl FnOnce(&mut HeapState) -> R) -> Result<R, String> {
+    match value {
+        Value::Handle(handle) if handle.kind() == "heap" => handle.with(name, f),
+        _ => Err(format!("{} espera um heap", name)),
+    }
+}
+
+impl NativeObject for VecDeque<Value> {
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        describe_items(f, "deque", self.iter())
+    }
+
+    fn duplicate(&self) -> Option<Box<dyn NativeObject>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+/// Fila de prioridade: sai primeiro a menor chave (ou a maior, com `max`); empates na ordem de inserção.
+#[derive(Clone)]
+struct HeapState {
+    heap: BinaryHeap<HeapEntry>,
+    key_fn: Option<Value>,
+    max: bool,
+    next_seq: u64,
+}
+
+impl HeapState {
+    fn push(&mut self, item: Value) -> Result<(), String> {
+        let key = match &self.key_fn {
+            Some(func) => call_function(func, vec![item.clone()])?,
+            None => item.clone(),
+        };
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.heap.push(HeapEntry { key, seq, item, max: self.max });
+        Ok(())
+    }
+
+    /// Itens na ordem em que sairiam com `heap_pop`.
+    fn sorted_items(&self) -> Vec<Value> {
+        self.heap.clone().into_sorted_vec().into_iter().rev().map(|entry| entry.item).collect()
+    }
+}
+
+impl NativeObject for HeapState {
+    fn describe(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        describe_items(f, "heap", self.sorted_items().iter())
+    }
+
+    fn duplicate(&self) -> Option<Box<dyn NativeObject>> {
+        Some(Box::new(self.clone()))
+    }
+}
+
+#[derive(Clone)]
+struct HeapEntry {
+    key: Value,
+    seq: u64,
+    item: Value,
+    max: bool,
+}
+
+impl Ord for HeapEntry {
+    /// `BinaryHeap` devolve o maior elemento; aqui "maior" é o que deve sair primeiro.
+    fn cmp(&self, other: &Self) -> Ordering {
+        let by_key = compare_values(&self.key, &other.key);
+        let by_key = if self.max { by_key } else { by_key.reverse() };
+        by_key.then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+/// `nlargest`/`nsmallest(coleção, n, key?)`: os n maiores/menores, do mais
+/// extremo para o menos extremo; empates mantêm a ordem original.
+fn select_extremes(name: &str, args: &[Value], largest: bool) -> Result<Value, String> {
+    let items = collection_items(name, &args[0])?;
+    let count = match &args[1] {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as usize,
+        _ => return Err(format!("{} espera um número inteiro não negativo", name)),
+    };
+    let key_fn = match args.get(2) {
+        None | Some(Value::Nil) => None,
+        Some(func) if is_callable(func) => Some(func),
+        Some(_) => return Err(format!("{} espera uma função como terceiro argumento", name)),
+    };
+
+    let mut decorated = Vec::with_capacity(items.len());
+    for item in items {
+        let key = match key_fn {
+            Some(func) => call_function(func, vec![item.clone()])?,
+            None => item.clone(),
+        };
+        decorated.push((key, item));
+    }
+    decorated.sort_by(|(ka, _), (kb, _)| {
+        let ordering = compare_values(ka, kb);
+        if largest { ordering.reverse() } else { ordering }
+    });
+    Ok(Value::List(decorated.into_iter().take(count).map(|(_, item)| item).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{call, numbers};
+
+    fn scrambled(count: u32) -> Value {
+        numbers((0..count).map(|n| ((n * 7919) % 1000) as f64))
+    }
+
+    #[test]
+    fn sort_by_tolerates_inconsistent_comparator() {
+        fn always_greater(_: Vec<Value>) -> Result<Value, String> {
+            Ok(Value::Number(1.0))
+        }
+        for n in [100, 1000] {
+            let sorted = call(&create_module(), "sort_by", vec![scrambled(n), Value::NativeFunction(always_greater)]).unwrap();
+            assert!(matches!(sorted, Value::List(items) if items.len() == n as usize));
+        }
+    }
+
+    #[test]
+    fn sort_by_stops_on_first_error() {
+        fn failing(args: Vec<Value>) -> Result<Value, String> {
+            match (&args[0], &args[1]) {
+                (Value::Number(a), Value::Number(b)) if *a == 500.0 || *b == 500.0 => Err("boom".to_string()),
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a - b)),
+                _ => unreachable!(),
+            }
+        }
+        let result = call(&create_module(), "sort_by", vec![scrambled(1000), Value::NativeFunction(failing)]);
+        assert_eq!(result, Err("boom".to_string()));
+    }
+
+    #[test]
+    fn sort_by_is_stable() {
+        fn by_tens(args: Vec<Value>) -> Result<Value, String> {
+            match (&args[0], &args[1]) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Number((a / 10.0).floor() - (b / 10.0).floor())),
+                _ => unreachable!(),
+            }
+        }
+        let list = numbers([15.0, 3.0, 12.0, 7.0, 11.0]);
+        let sorted = call(&create_module(), "sort_by", vec![list, Value::NativeFunction(by_tens)]).unwrap();
+        assert_eq!(sorted, numbers([3.0, 7.0, 15.0, 12.0, 11.0]));
+    }
+
+    #[test]
+    fn set_add_updates_the_set_in_place() {
+        let module = create_module();
+        let set = call(&module, "set", vec![]).unwrap();
+        for n in 0..50_000 {
+            call(&module, "set_add", vec![set.clone(), Value::Number((n % 40_000) as f64)]).unwrap();
+        }
+        assert_eq!(call(&module, "set_len", vec![set.clone()]).unwrap(), Value::Number(40_000.0));
+        assert_eq!(call(&module, "set_has", vec![set, Value::Number(39_999.0)]).unwrap(), Value::Boolean(true));
+    }
+
+    #[test]
+    fn sets_compare_by_content() {
+        let module = create_module();
+        let a = call(&module, "set", vec![numbers([1.0, 2.0, 3.0])]).unwrap();
+        let b = call(&module, "set", vec![numbers([3.0, 2.0, 1.0, 2.0])]).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.to_string(), "set(1, 2, 3)");
+
+        call(&module, "set_remove", vec![b.clone(), Value::Number(2.0)]).unwrap();
+        assert_ne!(a, b);
+        assert_eq!(call(&module, "collect", vec![b]).unwrap(), numbers([3.0, 1.0]));
+    }
+
+    #[test]
+    fn deep_clone_copies_sets() {
+        let module = create_module();
+        let set = call(&module, "set", vec![numbers([1.0, 2.0, 3.0])]).unwrap();
+        let copy = call(&module, "deep_clone", vec![Value::List(vec![set.clone()])]).unwrap();
+        let Value::List(items) = copy else { panic!("esperava uma lista") };
+        call(&module, "set_remove", vec![items[0].clone(), Value::Number(2.0)]).unwrap();
+        assert_eq!(call(&module, "set_len", vec![set]).unwrap(), Value::Number(3.0));
+        assert_eq!(call(&module, "collect", vec![items[0].clone()]).unwrap(), numbers([1.0, 3.0]));
+    }
+
+    #[test]
+    fn compare_values_ignores_dict_order() {
+        let entry = |key: &str, n: f64| (Value::String(key.to_string()), Value::Number(n));
+        let ab: IndexMap<Value, Value> = [entry("a", 1.0), entry("b", 2.0)].into_iter().collect();
+        let ba: IndexMap<Value, Value> = [entry("b", 2.0), entry("a", 1.0)].into_iter().collect();
+        assert_eq!(compare_values(&Value::Dict(ab), &Value::Dict(ba)), Ordering::Equal);
+    }
+
+    #[test]
+    fn grouping_keeps_first_seen_key_order() {
+        let module = create_module();
+        let words = Value::List(["pera", "uva", "pera", "kiwi", "uva", "pera"].iter()
+            .map(|w| Value::String(w.to_string()))
+            .collect());
+        let keys = |dict: Value| match dict {
+            Value::Dict(map) => map.keys().map(|k| k.to_string()).collect::<Vec<_>>(),
+            other => panic!("esperava um dicionário, veio {}", other),
+        };
+        let counts = call(&module, "frequencies", vec![words.clone()]).unwrap();
+        assert_eq!(keys(counts.clone()), ["pera", "uva", "kiwi"]);
+        if let Value::Dict(map) = &counts {
+            assert_eq!(map[&Value::String("pera".to_string())], Value::Number(3.0));
+        }
+
+        let identity = Value::NativeFunction(|args| Ok(args[0].clone()));
+        let groups = call(&module, "group_by", vec![words.clone(), identity.clone()]).unwrap();
+        assert_eq!(keys(groups), ["pera", "uva", "kiwi"]);
+        let counted = call(&module, "count_by", vec![words, identity]).unwrap();
+        assert_eq!(keys(counted), ["pera", "uva", "kiwi"]);
+    }
+
+    #[test]
+    fn range_is_lazy_and_exact() {
+        let module = create_module();
+        let range = |args: Vec<f64>| call(&module, "range", args.into_iter().map(Value::Number).collect()).unwrap();
+        let len = |args: Vec<f64>| call(&module, "len", vec![range(args)]).unwrap();
+
+        assert_eq!(len(vec![0.0, 1e6 + 0.0001]), Value::Number(1_000_001.0));
+        assert_eq!(len(vec![0.0, 1e10 + 0.4]), Value::Number(1e10 + 1.0));
+        assert_eq!(len(vec![10.0, -0.5, -3.0]), Value::Number(4.0));
+        assert_eq!(len(vec![0.0, 0.3, 0.1]), Value::Number(3.0));
+        assert_eq!(len(vec![1e6, 1e6 + 0.3, 0.1]), Value::Number(3.0));
+
+        let digits = range(vec![0.0, 10.0]);
+        let contains = |n: f64| call(&module, "contains", vec![digits.clone(), Value::Number(n)]).unwrap();
+        assert_eq!(contains(3.0), Value::Boolean(true));
+        assert_eq!(contains(3.000000001), Value::Boolean(false));
+        assert_eq!(contains(10.0), Value::Boolean(false));
+
+        let tenths = range(vec![0.0, 1.0, 0.1]);
+        let contains = |n: f64| call(&module, "contains", vec![tenths.clone(), Value::Number(n)]).unwrap();
+        assert_eq!(contains(0.3), Value::Boolean(true));
+        assert_eq!(contains(0.3000001), Value::Boolean(false));
+        assert_eq!(call(&module, "collect", vec![range(vec![0.0, 1.0, 0.25])]).unwrap(), numbers([0.0, 0.25, 0.5, 0.75]));
+    }
+
+    #[test]
+    fn deques_and_heaps_show_contents_and_deep_clone_copies() {
+        let module = create_module();
+        let run = |name: &str, args: Vec<Value>| call(&module, name, args).unwrap();
+
+        let deque = run("deque", vec![numbers([0.0, 919.0, 838.0])]);
+        run("push_front", vec![deque.clone(), Value::Number(-1.0)]);
+        assert_eq!(deque.to_string(), "deque(-1, 0, 919, 838)");
+        let copy = run("deep_clone", vec![deque.clone()]);
+        run("pop_back", vec![copy.clone()]);
+        assert_eq!(run("len", vec![deque.clone()]), Value::Number(4.0));
+        assert_eq!(copy.to_string(), "deque(-1, 0, 919)");
+
+        let heap = run("heap", vec![numbers([0.0, 919.0, 838.0, 757.0])]);
+        assert_eq!(heap.to_string(), "heap(0, 757, 838, 919)");
+        let copy = run("deep_clone", vec![heap.clone()]);
+        assert_eq!(run("heap_pop", vec![copy.clone()]), Value::Number(0.0));
+        assert_eq!(run("heap_peek", vec![heap.clone()]), Value::Number(0.0));
+
+        assert_eq!(run("release", vec![heap.clone()]), Value::Boolean(true));
+        assert_eq!(run("release", vec![heap.clone()]), Value::Boolean(false));
+        assert_eq!(heap.to_string(), "<heap liberado>");
+        assert_eq!(run("len", vec![copy]), Value::Number(3.0));
+    }
+
+    #[test]
+    fn released_heap_reports_an_error() {
+        let module = create_module();
+        let heap = call(&module, "heap", vec![numbers([1.0])]).unwrap();
+        call(&module, "release", vec![heap.clone()]).unwrap();
+        assert!(call(&module, "heap_pop", vec![heap]).unwrap_err().contains("já foi liberado"));
+    }
 }
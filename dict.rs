@@ -0,0 +1,284 @@
+use crate::value::Value;
+use super::collections::{call_function, is_callable};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+
+/// Cria e retorna o objeto do módulo `dict` com todas as suas funções.
+pub fn create_module() -> Value {
+    let mut module = HashMap::new();
+
+    module.insert("keys".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("dict.keys espera 1 argumento".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => Ok(Value::List(map.keys().cloned().collect())),
+            _ => Err("dict.keys espera um dicionário".to_string()),
+        }
+    }));
+
+    module.insert("values".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("dict.values espera 1 argumento".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => Ok(Value::List(map.values().cloned().collect())),
+            _ => Err("dict.values espera um dicionário".to_string()),
+        }
+    }));
+
+    module.insert("items".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("dict.items espera 1 argumento".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => {
+                let items = map.iter()
+                    .map(|(k, v)| Value::List(vec![k.clone(), v.clone()]))
+                    .collect();
+                Ok(Value::List(items))
+            },
+            _ => Err("dict.items espera um dicionário".to_string()),
+        }
+    }));
+
+    module.insert("has".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("dict.has espera 2 argumentos".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => Ok(Value::Boolean(map.contains_key(&args[1]))),
+            _ => Err("dict.has espera um dicionário e uma chave".to_string()),
+        }
+    }));
+
+    module.insert("get".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("dict.get espera 2 ou 3 argumentos".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => {
+                let default = args.get(2).cloned().unwrap_or(Value::Nil);
+                Ok(map.get(&args[1]).cloned().unwrap_or(default))
+            },
+            _ => Err("dict.get espera um dicionário e uma chave".to_string()),
+        }
+    }));
+
+    module.insert("set".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 3 { return Err("dict.set espera 3 argumentos".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => {
+                let mut updated = map.clone();
+                updated.insert(args[1].clone(), args[2].clone());
+                Ok(Value::Dict(updated))
+            },
+            _ => Err("dict.set espera um dicionário, uma chave e um valor".to_string()),
+        }
+    }));
+
+    module.insert("remove".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("dict.remove espera 2 argumentos".to_string()); }
+
+        match &args[0] {
+            Value::Dict(map) => {
+                let mut updated = map.clone();
+                updated.shift_remove(&args[1]);
+                Ok(Value::Dict(updated))
+            },
+            _ => Err("dict.remove espera um dicionário e uma chave".to_string()),
+        }
+    }));
+
+    module.insert("merge".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() { return Err("dict.merge espera pelo menos 1 argumento".to_string()); }
+
+        // Os dicionários da direita sobrescrevem as chaves dos da esquerda, que
+        // mantêm a posição; chaves novas entram no final.
+        let mut merged = IndexMap::new();
+        for arg in &args {
+            match arg {
+                Value::Dict(map) => {
+                    merged.extend(map.iter().map(|(k, v)| (k.clone(), v.clone())));
+                },
+                _ => return Err("dict.merge espera dicionários".to_string()),
+            }
+        }
+        Ok(Value::Dict(merged))
+    }));
+
+    module.insert("deep_merge".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() { return Err("dict.deep_merge espera pelo menos 1 argumento".to_string()); }
+
+        let mut merged = Value::Dict(IndexMap::new());
+        for arg in &args {
+            if !matches!(arg, Value::Dict(_)) {
+                return Err("dict.deep_merge espera dicionários".to_string());
+            }
+            merged = deep_merge(&merged, arg);
+        }
+        Ok(merged)
+    }));
+
+    module.insert("pick".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("dict.pick espera 2 argumentos".to_string()); }
+
+        match (&args[0], &args[1]) {
+            (Value::Dict(map), Value::List(keys)) => {
+                let picked = keys.iter()
+                    .filter_map(|k| map.get(k).map(|v| (k.clone(), v.clone())))
+                    .collect();
+                Ok(Value::Dict(picked))
+            },
+            _ => Err("dict.pick espera um dicionário e uma lista de chaves".to_string()),
+        }
+    }));
+
+    module.insert("omit".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("dict.omit espera 2 argumentos".to_string()); }
+
+        match (&args[0], &args[1]) {
+            (Value::Dict(map), Value::List(keys)) => {
+                let kept = map.iter()
+                    .filter(|(k, _)| !keys.contains(k))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect();
+                Ok(Value::Dict(kept))
+            },
+            _ => Err("dict.omit espera um dicionário e uma lista de chaves".to_string()),
+        }
+    }));
+
+    module.insert("from_pairs".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("dict.from_pairs espera 1 argumento".to_string()); }
+
+        match &args[0] {
+            Value::List(pairs) => {
+                let mut map = IndexMap::new();
+                for pair in pairs {
+                    match pair {
+                        Value::List(kv) if kv.len() == 2 => {
+                            map.insert(kv[0].clone(), kv[1].clone());
+                        },
+                        _ => return Err("dict.from_pairs espera uma lista de pares [chave, valor]".to_string()),
+                    }
+                }
+                Ok(Value::Dict(map))
+            },
+            _ => Err("dict.from_pairs espera uma lista de pares [chave, valor]".to_string()),
+        }
+    }));
+
+    module.insert("get_in".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("dict.get_in espera 2 ou 3 argumentos".to_string()); }
+
+        match &args[1] {
+            Value::List(path) => {
+                let default = args.get(2).cloned().unwrap_or(Value::Nil);
+                Ok(get_in(&args[0], path).cloned().unwrap_or(default))
+            },
+            _ => Err("dict.get_in espera uma lista de chaves como caminho".to_string()),
+        }
+    }));
+
+    module.insert("set_in".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 3 { return Err("dict.set_in espera 3 argumentos".to_string()); }
+
+        match &args[1] {
+            Value::List(path) => {
+                let new_value = args[2].clone();
+                update_in(&args[0], path, &mut |_| Ok(new_value.clone()))
+            },
+            _ => Err("dict.set_in espera uma lista de chaves como caminho".to_string()),
+        }
+    }));
+
+    module.insert("update_in".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 3 { return Err("dict.update_in espera 3 argumentos".to_string()); }
+
+        match (&args[1], &args[2]) {
+            (Value::List(path), func) if is_callable(func) => {
+                update_in(&args[0], path, &mut |current| call_function(func, vec![current]))
+            },
+            _ => Err("dict.update_in espera uma lista de chaves como caminho e uma função".to_string()),
+        }
+    }));
+
+    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
+    Value::Dict(dict_map)
+}
+
+/// Converte uma chave de caminho em índice de lista, aceitando índices negativos.
+fn list_index(key: &Value, len: usize) -> Option<usize> {
+    match key {
+        Value::Number(n) if n.fract() == 0.0 => {
+            let index = if *n < 0.0 { len as f64 + n } else { *n };
+            if index >= 0.0 && index < len as f64 { Some(index as usize) } else { None }
+        },
+        _ => None,
+    }
+}
+
+/// Segue `path` dentro de dicionários (por chave) e listas (por índice).
+fn get_in<'a>(value: &'a Value, path: &[Value]) -> Option<&'a Value> {
+    let mut current = value;
+    for key in path {
+        current = match current {
+            Value::Dict(map) => map.get(key)?,
+            Value::List(list) => &list[list_index(key, list.len())?],
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Devolve uma cópia de `value` com o nó em `path` substituído por `update(atual)`.
+/// Dicionários intermediários que não existem são criados; índices de lista
+/// precisam existir.
+fn update_in(
+    value: &Value,
+    path: &[Value],
+    update: &mut dyn FnMut(Value) -> Result<Value, String>,
+) -> Result<Value, String> {
+    let (key, rest) = match path.split_first() {
+        Some(split) => split,
+        None => return update(value.clone()),
+    };
+
+    match value {
+        Value::Dict(map) => {
+            let child = map.get(key).cloned().unwrap_or(Value::Nil);
+            let mut updated = map.clone();
+            updated.insert(key.clone(), update_in(&child, rest, update)?);
+            Ok(Value::Dict(updated))
+        },
+        Value::List(list) => {
+            let index = list_index(key, list.len())
+                .ok_or_else(|| format!("índice {} fora da lista", key))?;
+            let mut updated = list.clone();
+            updated[index] = update_in(&list[index], rest, update)?;
+            Ok(Value::List(updated))
+        },
+        Value::Nil => {
+            let mut created = IndexMap::new();
+            created.insert(key.clone(), update_in(&Value::Nil, rest, update)?);
+            Ok(Value::Dict(created))
+        },
+        _ => Err(format!("não é possível acessar a chave {} de um valor que não é dicionário nem lista", key)),
+    }
+}
+
+/// Mescla `right` sobre `left`; quando os dois lados de uma chave são
+/// dicionários, eles são mesclados recursivamente em vez de substituídos.
+fn deep_merge(left: &Value, right: &Value) -> Value {
+    match (left, right) {
+        (Value::Dict(left_map), Value::Dict(right_map)) => {
+            let mut merged = left_map.clone();
+            for (k, v) in right_map {
+                let value = match merged.get(k) {
+                    Some(existing) => deep_merge(existing, v),
+                    None => v.clone(),
+                };
+                merged.insert(k.clone(), value);
+            }
+            Value::Dict(merged)
+        },
+        (_, right) => right.clone(),
+    }
+}
@@ -0,0 +1,244 @@
+use crate::value::Value;
+use super::util::value_to_bytes;
+use std::collections::HashMap;
+
+/// Cria e retorna o objeto do módulo `encoding` com todas as suas funções.
+/// A conversão em si fica em `io.decode`/`io.encode`, que usam as funções daqui.
+pub fn create_module() -> Value {
+    let mut module = HashMap::new();
+
+    module.insert("detect".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("encoding.detect espera 1 argumento".to_string()); }
+
+        let bytes = value_to_bytes("encoding.detect", &args[0])?;
+        Ok(Value::String(detect(&bytes).to_string()))
+    }));
+
+    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
+    Value::Dict(dict_map)
+}
+
+/// Opções de codificação de `io.decode`/`io.encode` e das leituras e escritas de arquivos.
+/// Aceita `(nome)`, `({encoding, lossy, bom})` ou `(nome, {lossy, bom})`.
+pub struct CodecOptions {
+    /// Padrão "utf-8"; "auto" detecta ao decodificar.
+    pub encoding: String,
+    /// Troca o que não puder ser convertido por U+FFFD (ou `?` ao codificar).
+    pub lossy: bool,
+    /// Escreve a marca de ordem de bytes ao codificar em UTF-8/UTF-16.
+    pub bom: bool,
+}
+
+impl CodecOptions {
+    pub fn parse(name: &str, first: Option<&Value>, second: Option<&Value>) -> Result<Self, String> {
+        let mut options = CodecOptions { encoding: "utf-8".to_string(), lossy: false, bom: false };
+        let map = match (first, second) {
+            (None | Some(Value::Nil), None) => return Ok(options),
+            (Some(Value::Dict(map)), None) => map,
+            (Some(Value::String(encoding)), None | Some(Value::Nil)) => {
+                options.encoding = encoding.clone();
+                return Ok(options);
+            },
+            (Some(Value::String(encoding)), Some(Value::Dict(map))) => {
+                options.encoding = encoding.clone();
+                map
+            },
+            _ => return Err(format!("{} espera o nome da codificação e/ou um dicionário de opções", name)),
+        };
+
+        let field = |key: &str| map.get(&Value::String(key.to_string())).filter(|v| **v != Value::Nil);
+        let flag = |key: &str| match field(key) {
+            None => Ok(false),
+            Some(Value::Boolean(b)) => Ok(*b),
+            Some(_) => Err(format!("{} espera um booleano na opção '{}'", name, key)),
+        };
+        match field("encoding") {
+            None => {},
+            Some(Value::String(encoding)) => options.encoding = encoding.clone(),
+            Some(_) => return Err(format!("{} espera uma string na opção 'encoding'", name)),
+        }
+        options.lossy = flag("lossy")?;
+        options.bom = flag("bom")?;
+        Ok(options)
+    }
+}
+
+/// Nome canônico da codificação; aceita variações de caixa, `_` e apelidos comuns.
+pub fn canonical_name(encoding: &str) -> Result<&'static str, String> {
+    match encoding.to_lowercase().replace('_', "-").as_str() {
+        "utf-8" | "utf8" => Ok("utf-8"),
+        "utf-16" | "utf16" => Ok("utf-16"),
+        "utf-16le" | "utf-16-le" => Ok("utf-16le"),
+        "utf-16be" | "utf-16-be" => Ok("utf-16be"),
+        "latin1" | "latin-1" | "iso-8859-1" | "iso8859-1" => Ok("latin1"),
+        "windows-1252" | "cp1252" | "win-1252" => Ok("windows-1252"),
+        "ascii" | "us-ascii" => Ok("ascii"),
+        "auto" => Ok("auto"),
+        _ => Err(format!("Codificação desconhecida: {}", encoding)),
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Caracteres de 0x80 a 0x9F no Windows-1252; os demais bytes coincidem com o latin1.
+/// Os cinco bytes indefinidos viram os controles C1 de mesmo valor, como nos navegadores.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Adivinha a codificação: BOM, UTF-16 sem BOM (bytes nulos alternados), ASCII, UTF-8
+/// e por fim "windows-1252" (se houver bytes de 0x80 a 0x9F) ou "latin1".
+pub fn detect(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(UTF8_BOM) {
+        return "utf-8";
+    }
+    if bytes.starts_with(UTF16LE_BOM) {
+        return "utf-16le";
+    }
+    if bytes.starts_with(UTF16BE_BOM) {
+        return "utf-16be";
+    }
+
+    if bytes.len() >= 2 && bytes.len() % 2 == 0 {
+        let pairs = bytes.len() / 2;
+        let zeros_at = |offset: usize| bytes.iter().skip(offset).step_by(2).filter(|b| **b == 0).count();
+        // Texto latino em UTF-16 tem o byte alto nulo na maioria dos caracteres.
+        let (even, odd) = (zeros_at(0), zeros_at(1));
+        if odd * 10 >= pairs * 3 && even == 0 {
+            return "utf-16le";
+        }
+        if even * 10 >= pairs * 3 && odd == 0 {
+            return "utf-16be";
+        }
+    }
+
+    if bytes.is_ascii() {
+        "ascii"
+    } else if std::str::from_utf8(bytes).is_ok() {
+        "utf-8"
+    } else if bytes.iter().any(|b| (0x80..=0x9F).contains(b)) {
+        "windows-1252"
+    } else {
+        "latin1"
+    }
+}
+
+/// Decodifica `bytes`, removendo um BOM da codificação ("utf-16" usa o BOM para
+/// escolher a ordem; little-endian se não houver).
+pub fn decode(bytes: &[u8], encoding: &str, lossy: bool) -> Result<String, String> {
+    let encoding = match canonical_name(encoding)? {
+        "auto" => detect(bytes),
+        encoding => encoding,
+    };
+    match encoding {
+        "utf-8" => {
+            let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+            if lossy {
+                Ok(String::from_utf8_lossy(bytes).into_owned())
+            } else {
+                String::from_utf8(bytes.to_vec()).map_err(|e| format!("Bytes não são UTF-8 válido: {}", e))
+            }
+        },
+        "utf-16" => match bytes {
+            [0xFE, 0xFF, ..] => decode_utf16(&bytes[2..], false, lossy),
+            [0xFF, 0xFE, ..] => decode_utf16(&bytes[2..], true, lossy),
+            _ => decode_utf16(bytes, true, lossy),
+        },
+        "utf-16le" => decode_utf16(bytes.strip_prefix(UTF16LE_BOM).unwrap_or(bytes), true, lossy),
+        "utf-16be" => decode_utf16(bytes.strip_prefix(UTF16BE_BOM).unwrap_or(bytes), false, lossy),
+        "latin1" => Ok(bytes.iter().map(|b| *b as char).collect()),
+        "windows-1252" => Ok(bytes.iter().map(|b| match b {
+            0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+            _ => *b as char,
+        }).collect()),
+        "ascii" => match bytes.iter().position(|b| !b.is_ascii()) {
+            Some(_) if lossy => Ok(bytes.iter().map(|b| if b.is_ascii() { *b as char } else { '\u{FFFD}' }).collect()),
+            Some(pos) => Err(format!("Byte não ASCII na posição {}", pos)),
+            None => Ok(bytes.iter().map(|b| *b as char).collect()),
+        },
+        other => Err(format!("Codificação desconhecida: {}", other)),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool, lossy: bool) -> Result<String, String> {
+    if bytes.len() % 2 != 0 && !lossy {
+        return Err("UTF-16 espera um número par de bytes".to_string());
+    }
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    }).collect();
+    if lossy {
+        let mut text = String::from_utf16_lossy(&units);
+        if bytes.len() % 2 != 0 {
+            text.push('\u{FFFD}');
+        }
+        Ok(text)
+    } else {
+        String::from_utf16(&units).map_err(|e| format!("Bytes não são UTF-16 válido: {}", e))
+    }
+}
+
+/// Codifica `text`; "utf-16" sempre escreve o BOM, em little-endian.
+pub fn encode(text: &str, encoding: &str, bom: bool, lossy: bool) -> Result<Vec<u8>, String> {
+    let single_byte = |name: &str, to_byte: &dyn Fn(char) -> Option<u8>| -> Result<Vec<u8>, String> {
+        text.chars().map(|c| match to_byte(c) {
+            Some(b) => Ok(b),
+            None if lossy => Ok(b'?'),
+            None => Err(format!("Caractere '{}' não existe em {}", c, name)),
+        }).collect()
+    };
+
+    match canonical_name(encoding)? {
+        "utf-8" => {
+            let mut bytes = if bom { UTF8_BOM.to_vec() } else { Vec::new() };
+            bytes.extend_from_slice(text.as_bytes());
+            Ok(bytes)
+        },
+        encoding @ ("utf-16" | "utf-16le" | "utf-16be") => {
+            let little_endian = encoding != "utf-16be";
+            let mut bytes = match (bom || encoding == "utf-16", little_endian) {
+                (false, _) => Vec::new(),
+                (true, true) => UTF16LE_BOM.to_vec(),
+                (true, false) => UTF16BE_BOM.to_vec(),
+            };
+            for unit in text.encode_utf16() {
+                bytes.extend(if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() });
+            }
+            Ok(bytes)
+        },
+        "latin1" => single_byte("latin1", &|c| u8::try_from(c as u32).ok()),
+        "windows-1252" => single_byte("windows-1252", &|c| match c as u32 {
+            0x00..=0x7F | 0xA0..=0xFF => Some(c as u8),
+            _ => WINDOWS_1252_HIGH.iter().position(|high| *high == c).map(|i| 0x80 + i as u8),
+        }),
+        "ascii" => single_byte("ASCII", &|c| if c.is_ascii() { Some(c as u8) } else { None }),
+        _ => Err("A codificação \"auto\" só pode ser usada ao decodificar".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_and_round_trips_legacy_encodings() {
+        assert_eq!(detect("ação".as_bytes()), "utf-8");
+        assert_eq!(detect(&[b'a', 0xe7, 0xe3, b'o']), "latin1");
+        assert_eq!(detect(&[0x93, b'o', b'i', 0x94]), "windows-1252");
+        assert_eq!(detect(&[b'o', 0, b'i', 0]), "utf-16le");
+
+        let bytes = encode("“ação”", "cp1252", false, false).unwrap();
+        assert_eq!(bytes, [0x93, b'a', 0xe7, 0xe3, b'o', 0x94]);
+        assert_eq!(decode(&bytes, "auto", false).unwrap(), "“ação”");
+        assert!(decode(&[0xff], "utf-8", false).is_err());
+        assert_eq!(decode(&[b'a', 0xff], "utf-8", true).unwrap(), "a\u{FFFD}");
+        assert_eq!(encode("€", "latin1", false, true).unwrap(), b"?");
+    }
+}
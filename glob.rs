@@ -0,0 +1,405 @@
+use crate::value::Value;
+use super::vfs::{self, FileSystem};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Cria e retorna o objeto do módulo `glob` com todas as suas funções.
+pub fn create_module() -> Value {
+    let mut module = HashMap::new();
+
+    module.insert("matches".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("glob.matches espera 2 ou 3 argumentos".to_string()); }
+
+        match (&args[0], &args[1]) {
+            (Value::String(pattern), Value::String(path)) => {
+                let options = GlobOptions::parse("glob.matches", args.get(2))?;
+                Ok(Value::Boolean(matches(pattern, path, &options)?))
+            },
+            _ => Err("glob.matches espera duas strings (padrão e caminho)".to_string()),
+        }
+    }));
+
+    module.insert("filter".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("glob.filter espera 2 ou 3 argumentos".to_string()); }
+
+        match (&args[0], &args[1]) {
+            (Value::String(pattern), Value::List(paths)) => {
+                let options = GlobOptions::parse("glob.filter", args.get(2))?;
+                let mut matched = Vec::new();
+                for path in paths {
+                    match path {
+                        Value::String(p) => {
+                            if matches(pattern, p, &options)? {
+                                matched.push(path.clone());
+                            }
+                        },
+                        _ => return Err("glob.filter espera uma lista de strings".to_string()),
+                    }
+                }
+                Ok(Value::List(matched))
+            },
+            _ => Err("glob.filter espera um padrão e uma lista de caminhos".to_string()),
+        }
+    }));
+
+    module.insert("expand".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("glob.expand espera 1 ou 2 argumentos".to_string()); }
+
+        match &args[0] {
+            Value::String(pattern) => {
+                let options = GlobOptions::parse("glob.expand", args.get(1))?;
+                Ok(Value::List(expand(pattern, &options)?.into_iter().map(Value::String).collect()))
+            },
+            _ => Err("glob.expand espera uma string (padrão)".to_string()),
+        }
+    }));
+
+    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
+    Value::Dict(dict_map)
+}
+
+/// Opções aceitas pelas funções de glob.
+#[derive(Clone, Copy, Default)]
+pub struct GlobOptions {
+    /// Ignora maiúsculas/minúsculas.
+    pub case_insensitive: bool,
+    /// Curingas também casam nomes que começam com `.`.
+    pub dot: bool,
+}
+
+impl GlobOptions {
+    pub fn parse(name: &str, options: Option<&Value>) -> Result<Self, String> {
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(GlobOptions::default()),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err(format!("{} espera um dicionário de opções", name)),
+        };
+        let flag = |key: &str| match map.get(&Value::String(key.to_string())) {
+            None | Some(Value::Nil) => Ok(false),
+            Some(Value::Boolean(b)) => Ok(*b),
+            Some(_) => Err(format!("{} espera um booleano na opção '{}'", name, key)),
+        };
+        Ok(GlobOptions { case_insensitive: flag("case_insensitive")?, dot: flag("dot")? })
+    }
+}
+
+/// Verifica se `path` casa com `pattern`: `*`, `?`, classes (`[a-z]`, `[!a-z]`),
+/// `{a,b}`, `**` (zero ou mais diretórios) e `\` para escapar.
+pub fn matches(pattern: &str, path: &str, options: &GlobOptions) -> Result<bool, String> {
+    let path_segments = split_path(path);
+    for alternative in expand_braces(pattern)? {
+        let segments = parse_pattern(&alternative)?;
+        if match_segments(&segments, &path_segments, options) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Caminhos existentes que casam com `pattern`, sem repetições, em ordem alfabética
+/// e no mesmo formato (relativo ou absoluto) do padrão. A busca usa o sistema de arquivos de `io`.
+pub fn expand(pattern: &str, options: &GlobOptions) -> Result<Vec<String>, String> {
+    let filesystem = vfs::current();
+    let mut found = Vec::new();
+    for alternative in expand_braces(pattern)? {
+        let segments = parse_pattern(&alternative)?;
+        let (start, prefix) = if alternative.starts_with('/') {
+            (PathBuf::from("/"), "/".to_string())
+        } else {
+            (PathBuf::from("."), String::new())
+        };
+        expand_segments(filesystem.as_ref(), &start, &prefix, &segments, options, &mut found)?;
+    }
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+enum Token {
+    Literal(char),
+    AnyChar,
+    Star,
+    Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+enum Segment {
+    /// `**`: zero ou mais segmentos.
+    Recursive,
+    /// Segmento sem curingas, comparado (ou procurado no disco) diretamente.
+    Literal(String),
+    Pattern(Vec<Token>),
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    path.split('/').filter(|s| !s.is_empty() && *s != ".").collect()
+}
+
+/// Expande `{a,b}` (inclusive aninhados) em vários padrões sem chaves.
+fn expand_braces(pattern: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut open = None;
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            },
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let start = open.unwrap_or(0);
+                    let prefix: String = chars[..start].iter().collect();
+                    let suffix: String = chars[i + 1..].iter().collect();
+                    let mut bounds = vec![start];
+                    bounds.extend(&commas);
+                    bounds.push(i);
+                    let mut expanded = Vec::new();
+                    for window in bounds.windows(2) {
+                        let alternative: String = chars[window[0] + 1..window[1]].iter().collect();
+                        expanded.extend(expand_braces(&format!("{}{}{}", prefix, alternative, suffix))?);
+                    }
+                    return Ok(expanded);
+                }
+            },
+            _ => {},
+        }
+        i += 1;
+    }
+    if depth > 0 {
+        return Err(format!("padrão glob inválido: '{{' sem '}}' em '{}'", pattern));
+    }
+    Ok(vec![pattern.to_string()])
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Segment>, String> {
+    split_path(pattern).into_iter().map(|segment| {
+        if segment == "**" {
+            Ok(Segment::Recursive)
+        } else if segment.contains(['*', '?', '[', '\\']) {
+            Ok(Segment::Pattern(parse_segment(segment)?))
+        } else {
+            Ok(Segment::Literal(segment.to_string()))
+        }
+    }).collect()
+}
+
+fn parse_segment(segment: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = segment.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped) => tokens.push(Token::Literal(escaped)),
+                None => tokens.push(Token::Literal('\\')),
+            },
+            '*' => {
+                if !matches!(tokens.last(), Some(Token::Star)) {
+                    tokens.push(Token::Star);
+                }
+            },
+            '?' => tokens.push(Token::AnyChar),
+            '[' => {
+                let negated = matches!(chars.peek(), Some('!' | '^'));
+                if negated {
+                    chars.next();
+                }
+                let mut ranges = Vec::new();
+                let mut first = true;
+                loop {
+                    let start = match chars.next() {
+                        Some(']') if !first => break,
+                        Some('\\') => chars.next().unwrap_or('\\'),
+                        Some(c) => c,
+                        None => return Err(format!("padrão glob inválido: '[' sem ']' em '{}'", segment)),
+                    };
+                    first = false;
+                    let mut lookahead = chars.clone();
+                    if lookahead.next() == Some('-') && !matches!(lookahead.peek(), Some(']') | None) {
+                        chars.next();
+                        let end = chars.next().unwrap_or(start);
+                        ranges.push((start, end));
+                    } else {
+                        ranges.push((start, start));
+                    }
+                }
+                tokens.push(Token::Class { negated, ranges });
+            },
+            c => tokens.push(Token::Literal(c)),
+        }
+    }
+    Ok(tokens)
+}
+
+fn chars_equal(a: char, b: char, case_insensitive: bool) -> bool {
+    a == b || (case_insensitive && a.to_lowercase().eq(b.to_lowercase()))
+}
+
+fn token_matches(token: &Token, c: char, case_insensitive: bool) -> bool {
+    match token {
+        Token::Literal(l) => chars_equal(*l, c, case_insensitive),
+        Token::AnyChar => true,
+        Token::Star => false,
+        Token::Class { negated, ranges } => {
+            let in_range = |c: char| ranges.iter().any(|(lo, hi)| *lo <= c && c <= *hi);
+            let found = in_range(c) || (case_insensitive && c.to_lowercase().chain(c.to_uppercase()).any(in_range));
+            found != *negated
+        },
+    }
+}
+
+fn match_segment(segment: &Segment, name: &str, options: &GlobOptions) -> bool {
+    match segment {
+        Segment::Recursive => true,
+        Segment::Literal(literal) => {
+            literal.chars().count() == name.chars().count()
+                && literal.chars().zip(name.chars()).all(|(a, b)| chars_equal(a, b, options.case_insensitive))
+        },
+        Segment::Pattern(tokens) => {
+            // Nomes ocultos só casam com curingas se o padrão começar com '.' ou com a opção `dot`.
+            if name.starts_with('.') && !options.dot && !matches!(tokens.first(), Some(Token::Literal('.'))) {
+                return false;
+            }
+            match_tokens(tokens, &name.chars().collect::<Vec<_>>(), options.case_insensitive)
+        },
+    }
+}
+
+/// Casamento com backtracking só no último `*` visto (linear na prática).
+fn match_tokens(tokens: &[Token], text: &[char], case_insensitive: bool) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(Token::Star) => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(token) if token_matches(token, text[t], case_insensitive) => {
+                p += 1;
+                t += 1;
+            },
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    tokens[p..].iter().all(|token| matches!(token, Token::Star))
+}
+
+fn match_segments(segments: &[Segment], names: &[&str], options: &GlobOptions) -> bool {
+    match segments.split_first() {
+        None => names.is_empty(),
+        Some((Segment::Recursive, rest)) => {
+            (0..=names.len())
+                .take_while(|skip| names[..*skip].iter().all(|name| options.dot || !name.starts_with('.')))
+                .any(|skip| match_segments(rest, &names[skip..], options))
+        },
+        Some((segment, rest)) => {
+            !names.is_empty() && match_segment(segment, names[0], options) && match_segments(rest, &names[1..], options)
+        },
+    }
+}
+
+fn join_display(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() || prefix.ends_with('/') {
+        format!("{}{}", prefix, name)
+    } else {
+        format!("{}/{}", prefix, name)
+    }
+}
+
+/// Nomes das entradas de `dir` em ordem alfabética; diretórios ilegíveis são ignorados.
+fn dir_entries(filesystem: &dyn FileSystem, dir: &Path) -> Result<Vec<(String, PathBuf)>, String> {
+    let entries = match filesystem.read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let name = entry.into_string()
+            .map_err(|name| format!("caminho não é UTF-8 válido: {}", dir.join(name).display()))?;
+        let path = dir.join(&name);
+        names.push((name, path));
+    }
+    Ok(names)
+}
+
+fn expand_segments(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    display: &str,
+    segments: &[Segment],
+    options: &GlobOptions,
+    found: &mut Vec<String>,
+) -> Result<(), String> {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            if !display.is_empty() {
+                found.push(display.to_string());
+            }
+            return Ok(());
+        },
+    };
+
+    match segment {
+        Segment::Literal(name) if !options.case_insensitive => {
+            let path = dir.join(name);
+            let is_dir = || filesystem.metadata(&path).is_ok_and(|info| info.is_dir());
+            if filesystem.symlink_metadata(&path).is_ok() && (rest.is_empty() || is_dir()) {
+                expand_segments(filesystem, &path, &join_display(display, name), rest, options, found)?;
+            }
+        },
+        Segment::Recursive => {
+            // Zero diretórios...
+            expand_segments(filesystem, dir, display, rest, options, found)?;
+            // ...ou mais um nível. Links para diretórios não são seguidos (evita ciclos).
+            for (name, path) in dir_entries(filesystem, dir)? {
+                let is_real_dir = filesystem.symlink_metadata(&path).is_ok_and(|info| info.is_dir());
+                if is_real_dir && (options.dot || !name.starts_with('.')) {
+                    expand_segments(filesystem, &path, &join_display(display, &name), segments, options, found)?;
+                }
+            }
+        },
+        segment => {
+            for (name, path) in dir_entries(filesystem, dir)? {
+                let is_dir = || filesystem.metadata(&path).is_ok_and(|info| info.is_dir());
+                if match_segment(segment, &name, options) && (rest.is_empty() || is_dir()) {
+                    expand_segments(filesystem, &path, &join_display(display, &name), rest, options, found)?;
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_classes_braces_and_double_star() {
+        let options = GlobOptions::default();
+        assert!(matches("src/**/*.rs", "src/a/b/lib.rs", &options).unwrap());
+        assert!(matches("src/**/*.rs", "src/lib.rs", &options).unwrap());
+        assert!(matches("img/*.{png,jp[e]g}", "img/a.jpeg", &options).unwrap());
+        assert!(!matches("[!a-c]*", "banana", &options).unwrap());
+        assert!(!matches("*", ".hidden", &options).unwrap());
+        assert!(matches("*", ".hidden", &GlobOptions { dot: true, ..options }).unwrap());
+        assert!(matches("*.TXT", "notes.txt", &GlobOptions { case_insensitive: true, ..options }).unwrap());
+    }
+}
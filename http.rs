@@ -14,7 +14,7 @@ pub fn create_module() -> Value {
                 {
                     match reqwest::blocking::get(url) {
                         Ok(response) => {
-                            let mut result = HashMap::new();
+                            let mut result = indexmap::IndexMap::new();
                             
                             // Status code
                             result.insert(
@@ -43,6 +43,7 @@ pub fn create_module() -> Value {
                 
                 #[cfg(not(feature = "http"))]
                 {
+                    let _ = url;
                     Err("HTTP não está habilitado nesta build".to_string())
                 }
             },
@@ -60,7 +61,7 @@ pub fn create_module() -> Value {
                     let client = reqwest::blocking::Client::new();
                     match client.post(url).body(body.clone()).send() {
                         Ok(response) => {
-                            let mut result = HashMap::new();
+                            let mut result = indexmap::IndexMap::new();
                             
                             result.insert(
                                 Value::String("status".to_string()),
@@ -87,6 +88,7 @@ pub fn create_module() -> Value {
                 
                 #[cfg(not(feature = "http"))]
                 {
+                    let _ = (url, body);
                     Err("HTTP não está habilitado nesta build".to_string())
                 }
             },
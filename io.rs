@@ -1,19 +1,68 @@
 use crate::value::Value;
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use super::collections::{self, Handle, NativeObject, WeakHandle};
+use super::encoding;
+use super::glob;
+use super::util::{bytes_to_value, home_dir, path_to_string, value_to_bytes};
+use super::vfs::{self, FileInfo, FileKind, FileStream, FileSystem, LockGuard, OpenMode};
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, Metadata, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    /// Recursos abertos pelo módulo (arquivos, travas, temporários e `watch`), para `close_all`. Guardam
+    /// só referências fracas: o recurso é fechado quando o último `Value` que o usa some.
+    static RESOURCES: RefCell<Vec<WeakHandle>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Fecha os arquivos abertos com `io.open`, gravando o que estiver em buffer, libera as
+/// travas e apaga os temporários (exceto os criados com `keep`).
+/// Registrado por `stdlib::init` em `sys::at_exit`, já que `std::process::exit` não executa destrutores.
+pub fn close_all() {
+    for resource in RESOURCES.with(|resources| std::mem::take(&mut *resources.borrow_mut())) {
+        if let Some(handle) = resource.upgrade() {
+            let _ = handle.release("io.close_all");
+        }
+    }
+}
+
+/// Cria um objeto nativo `kind` com `fields` e o recurso guardado num `Handle`
+/// (campo `handle`), registrado para `close_all`.
+fn track(kind: &'static str, mut fields: Vec<(&str, Value)>, object: impl NativeObject) -> Value {
+    let handle = Handle::new(kind, object);
+    RESOURCES.with(|resources| {
+        let mut resources = resources.borrow_mut();
+        resources.retain(|resource| resource.upgrade().is_some());
+        resources.push(handle.downgrade());
+    });
+    fields.push(("handle", Value::Handle(handle)));
+    collections::new_object(kind, fields)
+}
+
+/// O `Handle` de um objeto criado por `track`.
+fn resource<'a>(value: &'a Value, kind: &str) -> Option<&'a Handle> {
+    match collections::object_field(value, "handle") {
+        Some(Value::Handle(handle)) if handle.kind() == kind && collections::object_kind(value) == Some(kind) => Some(handle),
+        _ => None,
+    }
+}
 
 /// Cria e retorna o objeto do módulo `io` com todas as suas funções.
 pub fn create_module() -> Value {
     let mut module = HashMap::new();
 
     module.insert("read_file".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 1 { return Err("io.read_file espera 1 argumento".to_string()); }
+        if args.is_empty() || args.len() > 2 { return Err("io.read_file espera 1 ou 2 argumentos".to_string()); }
         
         match &args[0] {
             Value::String(path) => {
-                match fs::read_to_string(path) {
-                    Ok(content) => Ok(Value::String(content)),
+                // Opções de codificação como em `encoding.decode` (padrão UTF-8 estrito).
+                let options = encoding::CodecOptions::parse("io.read_file", args.get(1), None)?;
+                match vfs::current().read(Path::new(path)) {
+                    Ok(bytes) => Ok(Value::String(encoding::decode(&bytes, &options.encoding, options.lossy)?)),
                     Err(e) => Err(format!("Erro ao ler arquivo: {}", e)),
                 }
             },
@@ -22,11 +71,13 @@ pub fn create_module() -> Value {
     }));
 
     module.insert("write_file".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 2 { return Err("io.write_file espera 2 argumentos".to_string()); }
+        if args.len() < 2 || args.len() > 3 { return Err("io.write_file espera 2 ou 3 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
             (Value::String(path), Value::String(content)) => {
-                match fs::write(path, content) {
+                let options = encoding::CodecOptions::parse("io.write_file", args.get(2), None)?;
+                let bytes = encoding::encode(content, &options.encoding, options.bom, options.lossy)?;
+                match vfs::current().write(Path::new(path), &bytes) {
                     Ok(_) => Ok(Value::Boolean(true)),
                     Err(e) => Err(format!("Erro ao escrever arquivo: {}", e)),
                 }
@@ -36,24 +87,24 @@ pub fn create_module() -> Value {
     }));
 
     module.insert("append_file".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 2 { return Err("io.append_file espera 2 argumentos".to_string()); }
+        if args.len() < 2 || args.len() > 3 { return Err("io.append_file espera 2 ou 3 argumentos".to_string()); }
         
         match (&args[0], &args[1]) {
             (Value::String(path), Value::String(content)) => {
-                use std::fs::OpenOptions;
-                use std::io::Write;
-
-                match OpenOptions::new().create(true).append(true).open(path) {
-                    Ok(mut file) => {
-                        match file.write_all(content.as_bytes()) {
-                            Ok(_) => Ok(Value::Boolean(true)),
-                            Err(e) => Err(format!("Erro ao adicionar ao arquivo: {}", e)),
-                        }
-                    },
-                    Err(e) => Err(format!("Erro ao abrir arquivo: {}", e)),
+                let options = encoding::CodecOptions::parse("io.append_file", args.get(2), None)?;
+                let mut bytes = encoding::encode(content, &options.encoding, options.bom, options.lossy)?;
+                // O BOM só vale no início do arquivo: não é repetido ao adicionar a um arquivo com conteúdo.
+                let filesystem = vfs::current();
+                if filesystem.metadata(Path::new(path)).is_ok_and(|info| info.len > 0) {
+                    let bom = encoding::encode("", &options.encoding, options.bom, options.lossy)?;
+                    bytes.drain(..bom.len());
+                }
+                match filesystem.append(Path::new(path), &bytes) {
+                    Ok(_) => Ok(Value::Boolean(true)),
+                    Err(e) => Err(format!("Erro ao adicionar ao arquivo: {}", e)),
                 }
             },
-            _ => Err("io.append_file espera duas strings (caminho e conteúdo)".to_string()),
+            _ => Err("io.append_file espera duas strings (caminho e conteúdo) e, opcionalmente, a codificação".to_string()),
         }
     }));
 
@@ -62,23 +113,45 @@ pub fn create_module() -> Value {
         
         match &args[0] {
             Value::String(path) => {
-                Ok(Value::Boolean(Path::new(path).exists()))
+                Ok(Value::Boolean(vfs::current().metadata(Path::new(path)).is_ok()))
             },
             _ => Err("io.exists espera uma string (caminho)".to_string()),
         }
     }));
 
     module.insert("delete".to_string(), Value::NativeFunction(|args| {
-        if args.len() != 1 { return Err("io.delete espera 1 argumento".to_string()); }
+        if args.is_empty() || args.len() > 2 { return Err("io.delete espera 1 ou 2 argumentos".to_string()); }
         
         match &args[0] {
             Value::String(path) => {
+                let options = DeleteOptions::parse(args.get(1))?;
+                let filesystem = vfs::current();
                 let path_obj = Path::new(path);
-                
-                let result = if path_obj.is_dir() {
-                    fs::remove_dir_all(path)
+                let info = filesystem.symlink_metadata(path_obj)
+                    .map_err(|e| format!("Erro ao deletar: {}", e))?;
+                check_deletable(filesystem.as_ref(), path_obj, &info, options.recursive)?;
+
+                if options.dry_run {
+                    let mut removed = Vec::new();
+                    deletion_list(filesystem.as_ref(), path_obj, &info, &mut removed)?;
+                    return Ok(Value::List(removed));
+                }
+
+                // `info` vem de lstat: um link para diretório é só um link, e o alvo nunca é tocado.
+                let result = if options.trash {
+                    // A lixeira fica fora de qualquer raiz virtual; só vale para o disco direto.
+                    if !vfs::is_disk() {
+                        return Err("io.delete: a lixeira só está disponível no sistema de arquivos real".to_string());
+                    }
+                    move_to_trash(path_obj)
+                } else if info.is_symlink() {
+                    // No Windows, links para diretórios são removidos com remove_dir.
+                    filesystem.remove_file(path_obj)
+                        .or_else(|e| if cfg!(windows) { filesystem.remove_dir(path_obj) } else { Err(e) })
+                } else if info.is_dir() {
+                    filesystem.remove_dir_all(path_obj)
                 } else {
-                    fs::remove_file(path)
+                    filesystem.remove_file(path_obj)
                 };
 
                 match result {
@@ -95,16 +168,12 @@ pub fn create_module() -> Value {
         
         match &args[0] {
             Value::String(path) => {
-                match fs::read_dir(path) {
-                    Ok(entries) => {
+                match vfs::current().read_dir(Path::new(path)) {
+                    Ok(names) => {
                         let mut files = Vec::new();
-                        for entry in entries {
-                            if let Ok(entry) = entry {
-                                if let Some(name) = entry.path().file_name() {
-                                    if let Some(name_str) = name.to_str() {
-                                        files.push(Value::String(name_str.to_string()));
-                                    }
-                                }
+                        for name in names {
+                            if let Some(name_str) = name.to_str() {
+                                files.push(Value::String(name_str.to_string()));
                             }
                         }
                         Ok(Value::List(files))
@@ -121,7 +190,7 @@ pub fn create_module() -> Value {
         
         match &args[0] {
             Value::String(path) => {
-                Ok(Value::Boolean(Path::new(path).is_file()))
+                Ok(Value::Boolean(vfs::current().metadata(Path::new(path)).is_ok_and(|info| info.is_file())))
             },
             _ => Err("io.is_file espera uma string (caminho)".to_string()),
         }
@@ -132,18 +201,84 @@ pub fn create_module() -> Value {
         
         match &args[0] {
             Value::String(path) => {
-                Ok(Value::Boolean(Path::new(path).is_dir()))
+                Ok(Value::Boolean(vfs::current().metadata(Path::new(path)).is_ok_and(|info| info.is_dir())))
             },
             _ => Err("io.is_dir espera uma string (caminho)".to_string()),
         }
     }));
 
+    module.insert("symlink".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.symlink espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (Value::String(target), Value::String(link)) => {
+                // O alvo é gravado como está: relativo ao diretório do link e não precisa existir.
+                match vfs::current().symlink(Path::new(target), Path::new(link)) {
+                    Ok(_) => Ok(Value::Boolean(true)),
+                    Err(e) => Err(format!("Erro ao criar link {}: {}", link, e)),
+                }
+            },
+            _ => Err("io.symlink espera duas strings (alvo e link)".to_string()),
+        }
+    }));
+    
+    module.insert("hardlink".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.hardlink espera 2 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (Value::String(src), Value::String(dst)) => {
+                match vfs::current().hard_link(Path::new(src), Path::new(dst)) {
+                    Ok(_) => Ok(Value::Boolean(true)),
+                    Err(e) => Err(format!("Erro ao criar link {}: {}", dst, e)),
+                }
+            },
+            _ => Err("io.hardlink espera duas strings (arquivo existente e novo caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("read_link".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.read_link espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let target = vfs::current().read_link(Path::new(path))
+                    .map_err(|e| format!("Erro ao ler link {}: {}", path, e))?;
+                Ok(Value::String(path_to_string(&target)?))
+            },
+            _ => Err("io.read_link espera uma string (caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("is_symlink".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.is_symlink espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                Ok(Value::Boolean(vfs::current().symlink_metadata(Path::new(path)).is_ok_and(|info| info.is_symlink())))
+            },
+            _ => Err("io.is_symlink espera uma string (caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("canonicalize".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.canonicalize espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let canonical = vfs::current().canonicalize(Path::new(path))
+                    .map_err(|e| format!("Erro ao resolver {}: {}", path, e))?;
+                Ok(Value::String(path_to_string(&canonical)?))
+            },
+            _ => Err("io.canonicalize espera uma string (caminho)".to_string()),
+        }
+    }));
+    
     module.insert("create_dir".to_string(), Value::NativeFunction(|args| {
         if args.len() != 1 { return Err("io.create_dir espera 1 argumento".to_string()); }
         
         match &args[0] {
             Value::String(path) => {
-                match fs::create_dir_all(path) {
+                match vfs::current().create_dir_all(Path::new(path)) {
                     Ok(_) => Ok(Value::Boolean(true)),
                     Err(e) => Err(format!("Erro ao criar diretório: {}", e)),
                 }
@@ -152,6 +287,2261 @@ pub fn create_module() -> Value {
         }
     }));
     
+    module.insert("copy".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("io.copy espera 2 ou 3 argumentos".to_string()); }
+        
+        match (&args[0], &args[1]) {
+            (Value::String(src), Value::String(dst)) => {
+                let options = CopyOptions::parse("io.copy", args.get(2))?;
+                let filesystem = vfs::current();
+                let (src, dst) = (Path::new(src), Path::new(dst));
+                if same_file(&*filesystem, src, dst, true) {
+                    return Err(format!("io.copy: origem e destino são o mesmo arquivo: {}", dst.display()));
+                }
+                let info = filesystem.metadata(src).map_err(|e| format!("Erro ao ler {}: {}", src.display(), e))?;
+                if info.is_dir() {
+                    if !options.recursive {
+                        return Err(format!("io.copy: {} é um diretório (use a opção recursive)", src.display()));
+                    }
+                    let dst_parent = match dst.parent() {
+                        Some(parent) if parent.as_os_str().is_empty() => Some(Path::new(".")),
+                        parent => parent,
+                    };
+                    if let (Ok(src_real), Some(Ok(dst_parent))) = (filesystem.canonicalize(src), dst_parent.map(|parent| filesystem.canonicalize(parent))) {
+                        if dst_parent.starts_with(&src_real) {
+                            return Err("io.copy: não é possível copiar um diretório para dentro dele mesmo".to_string());
+                        }
+                    }
+                }
+                if options.conflict == Conflict::Error && filesystem.symlink_metadata(dst).is_ok() {
+                    return Err(format!("io.copy: destino já existe: {}", dst.display()));
+                }
+                let copied = copy_entry(&*filesystem, src, dst, &info, &options)?;
+                Ok(Value::Number(copied as f64))
+            },
+            _ => Err("io.copy espera duas strings (origem e destino)".to_string()),
+        }
+    }));
+    
+    module.insert("move".to_string(), Value::NativeFunction(|args| move_path("io.move", &args)));
+    module.insert("rename".to_string(), Value::NativeFunction(|args| move_path("io.rename", &args)));
+    
+    module.insert("write_atomic".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.write_atomic espera 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let bytes = match &args[1] {
+                    Value::String(content) => content.clone().into_bytes(),
+                    other => value_to_bytes("io.write_atomic", other)?,
+                };
+                write_atomic(vfs::current().as_ref(), Path::new(path), &bytes)
+                    .map_err(|e| format!("Erro ao escrever arquivo: {}", e))?;
+                Ok(Value::Boolean(true))
+            },
+            _ => Err("io.write_atomic espera uma string (caminho) e o conteúdo (string ou bytes)".to_string()),
+        }
+    }));
+    
+    module.insert("lock".to_string(), Value::NativeFunction(|args| lock_path("io.lock", &args, true)));
+    module.insert("try_lock".to_string(), Value::NativeFunction(|args| lock_path("io.try_lock", &args, false)));
+    
+    module.insert("unlock".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.unlock espera 1 argumento".to_string()); }
+        
+        let handle = resource(&args[0], "lock")
+            .ok_or_else(|| "io.unlock espera uma trava obtida com io.lock".to_string())?;
+        // Liberar duas vezes não é erro; retorna false se já estava liberada.
+        if let Ok(result) = handle.with("io.unlock", |lock: &mut FileLock| lock.guard.unlock()) {
+            result.map_err(|e| format!("Erro ao liberar trava: {}", e))?;
+        }
+        Ok(Value::Boolean(handle.release("io.unlock")?))
+    }));
+    
+    module.insert("temp_file".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 3 { return Err("io.temp_file espera até 3 argumentos".to_string()); }
+        
+        let prefix = optional_string("io.temp_file", args.first())?;
+        let suffix = optional_string("io.temp_file", args.get(1))?;
+        let options = TempOptions::parse("io.temp_file", args.get(2))?;
+        create_temp(&prefix, &suffix, false, options).map_err(|e| format!("Erro ao criar arquivo temporário: {}", e))
+    }));
+    
+    module.insert("temp_dir".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 2 { return Err("io.temp_dir espera até 2 argumentos".to_string()); }
+        
+        let prefix = optional_string("io.temp_dir", args.first())?;
+        let options = TempOptions::parse("io.temp_dir", args.get(1))?;
+        create_temp(&prefix, "", true, options).map_err(|e| format!("Erro ao criar diretório temporário: {}", e))
+    }));
+    
+    module.insert("remove_temp".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.remove_temp espera 1 argumento".to_string()); }
+        
+        let handle = resource(&args[0], "temp")
+            .ok_or_else(|| "io.remove_temp espera um temporário criado com io.temp_file ou io.temp_dir".to_string())?;
+        // Remover duas vezes não é erro; retorna false se já tinha sido removido.
+        // Sem io.remove_temp ele é apagado quando deixa de ser referenciado.
+        if let Ok(result) = handle.with("io.remove_temp", |entry: &mut TempEntry| {
+            entry.keep = true;
+            entry.remove()
+        }) {
+            result.map_err(|e| format!("Erro ao remover temporário: {}", e))?;
+        }
+        Ok(Value::Boolean(handle.release("io.remove_temp")?))
+    }));
+    
+    module.insert("stat".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.stat espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let filesystem = vfs::current();
+                let info = filesystem.metadata(Path::new(path)).map_err(|e| format!("Erro ao ler {}: {}", path, e))?;
+                let is_symlink = filesystem.symlink_metadata(Path::new(path)).is_ok_and(|link| link.is_symlink());
+                let host = filesystem.host_path(Path::new(path)).and_then(|host| fs::metadata(host).ok());
+                metadata_record(path, &info, is_symlink, host.as_ref())
+            },
+            _ => Err("io.stat espera uma string (caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("lstat".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.lstat espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let filesystem = vfs::current();
+                let info = filesystem.symlink_metadata(Path::new(path)).map_err(|e| format!("Erro ao ler {}: {}", path, e))?;
+                let host = filesystem.host_path(Path::new(path)).and_then(|host| fs::symlink_metadata(host).ok());
+                metadata_record(path, &info, info.is_symlink(), host.as_ref())
+            },
+            _ => Err("io.lstat espera uma string (caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("set_mtime".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.set_mtime espera 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let time = value_to_time("io.set_mtime", &args[1])?;
+                vfs::current().set_times(Path::new(path), time, None)
+                    .map_err(|e| format!("Erro ao alterar data de {}: {}", path, e))?;
+                Ok(Value::Boolean(true))
+            },
+            _ => Err("io.set_mtime espera uma string (caminho) e um número (segundos desde 1970)".to_string()),
+        }
+    }));
+    
+    module.insert("touch".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.touch espera 1 ou 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let time = match args.get(1) {
+                    None | Some(Value::Nil) => SystemTime::now(),
+                    Some(value) => value_to_time("io.touch", value)?,
+                };
+                // Cria o arquivo se não existir, sem alterar o conteúdo; diretórios também são aceitos.
+                let filesystem = vfs::current();
+                let path_ref = Path::new(path);
+                let created = match filesystem.metadata(path_ref) {
+                    Ok(_) => Ok(()),
+                    Err(_) => filesystem.append(path_ref, &[]),
+                };
+                created.and_then(|_| filesystem.set_times(path_ref, time, Some(time)))
+                    .map_err(|e| format!("Erro ao tocar {}: {}", path, e))?;
+                Ok(Value::Boolean(true))
+            },
+            _ => Err("io.touch espera uma string (caminho)".to_string()),
+        }
+    }));
+    
+    module.insert("read_bytes".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.read_bytes espera 1 argumento".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                match vfs::current().read(Path::new(path)) {
+                    Ok(content) => Ok(bytes_to_value(&content)),
+                    Err(e) => Err(format!("Erro ao ler arquivo: {}", e)),
+                }
+            },
+            _ => Err("io.read_bytes espera uma string (caminho do arquivo)".to_string()),
+        }
+    }));
+
+    module.insert("write_bytes".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.write_bytes espera 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let bytes = value_to_bytes("io.write_bytes", &args[1])?;
+                match vfs::current().write(Path::new(path), &bytes) {
+                    Ok(_) => Ok(Value::Boolean(true)),
+                    Err(e) => Err(format!("Erro ao escrever arquivo: {}", e)),
+                }
+            },
+            _ => Err("io.write_bytes espera uma string (caminho) e uma lista de bytes".to_string()),
+        }
+    }));
+
+    module.insert("append_bytes".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.append_bytes espera 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let bytes = value_to_bytes("io.append_bytes", &args[1])?;
+                match vfs::current().append(Path::new(path), &bytes) {
+                    Ok(_) => Ok(Value::Boolean(true)),
+                    Err(e) => Err(format!("Erro ao adicionar ao arquivo: {}", e)),
+                }
+            },
+            _ => Err("io.append_bytes espera uma string (caminho) e uma lista de bytes".to_string()),
+        }
+    }));
+
+    module.insert("decode".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 3 { return Err("io.decode espera de 1 a 3 argumentos".to_string()); }
+        
+        let bytes = value_to_bytes("io.decode", &args[0])?;
+        let options = encoding::CodecOptions::parse("io.decode", args.get(1), args.get(2))?;
+        Ok(Value::String(encoding::decode(&bytes, &options.encoding, options.lossy)?))
+    }));
+
+    module.insert("encode".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 3 { return Err("io.encode espera de 1 a 3 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(text) => {
+                let options = encoding::CodecOptions::parse("io.encode", args.get(1), args.get(2))?;
+                Ok(bytes_to_value(&encoding::encode(text, &options.encoding, options.bom, options.lossy)?))
+            },
+            _ => Err("io.encode espera uma string e, opcionalmente, o nome da codificação".to_string()),
+        }
+    }));
+    
+    module.insert("open".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.open espera 1 ou 2 argumentos".to_string()); }
+        
+        let mode = match args.get(1) {
+            None => "r".to_string(),
+            Some(Value::String(mode)) => mode.clone(),
+            Some(_) => return Err("io.open espera uma string como modo".to_string()),
+        };
+        // Com `b` (ex.: "rb", "r+b") as leituras retornam bytes em vez de texto.
+        let binary = mode.contains('b');
+        let base = mode.replacen('b', "", 1);
+        match &args[0] {
+            Value::String(path) => {
+                let mut options = OpenMode::default();
+                let (readable, writable) = match base.as_str() {
+                    "r" => (true, false),
+                    "w" => { options.create = true; options.truncate = true; (false, true) },
+                    "a" => { options.create = true; options.append = true; (false, true) },
+                    "r+" => (true, true),
+                    "w+" => { options.create = true; options.truncate = true; (true, true) },
+                    "a+" => { options.create = true; options.append = true; (true, true) },
+                    _ => return Err(format!("io.open: modo inválido '{}' (use r, w, a, r+, w+ ou a+, com b opcional)", mode)),
+                };
+                options.read = readable;
+                options.write = writable && !options.append;
+                let file = match vfs::current().open(Path::new(path), options) {
+                    Ok(file) => file,
+                    Err(e) => return Err(format!("Erro ao abrir arquivo: {}", e)),
+                };
+                let handle = FileHandle {
+                    stream: Some(Stream::Reader(BufReader::new(file))),
+                    path: path.clone(),
+                    mode: mode.clone(),
+                    readable,
+                    writable,
+                    binary,
+                };
+                Ok(track("file", vec![
+                    ("path", Value::String(path.clone())),
+                    ("mode", Value::String(mode)),
+                ], handle))
+            },
+            _ => Err("io.open espera uma string (caminho do arquivo)".to_string()),
+        }
+    }));
+
+    module.insert("read".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.read espera 1 ou 2 argumentos".to_string()); }
+        
+        let limit = match args.get(1) {
+            None | Some(Value::Nil) => None,
+            Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as u64),
+            Some(_) => return Err("io.read espera um número inteiro não negativo de bytes".to_string()),
+        };
+        let (bytes, binary) = with_file("io.read", &args[0], |handle| {
+            let binary = handle.binary;
+            handle.read(limit, !binary).map(|bytes| (bytes, binary))
+        })?;
+        if binary {
+            return Ok(bytes_to_value(&bytes));
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(Value::String(text)),
+            Err(_) => Err("Erro ao ler arquivo: conteúdo não é UTF-8 válido (abra com o modo \"rb\")".to_string()),
+        }
+    }));
+
+    module.insert("read_line".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.read_line espera 1 argumento".to_string()); }
+        
+        read_line("io.read_line", &args[0])
+    }));
+
+    module.insert("lines".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.lines espera 1 argumento".to_string()); }
+        if resource(&args[0], "file").is_none() {
+            return Err("io.lines espera um arquivo aberto com io.open".to_string());
+        }
+        
+        // Iterador preguiçoso: cada item lê a próxima linha do arquivo.
+        Ok(collections::new_generator(|state| {
+            match read_line("io.lines", &state[0])? {
+                Value::Nil => Ok(Value::Nil),
+                line => Ok(Value::List(vec![line])),
+            }
+        }, args[0].clone()))
+    }));
+
+    module.insert("write".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("io.write espera 2 argumentos".to_string()); }
+        
+        let bytes = match &args[1] {
+            Value::String(content) => content.as_bytes().to_vec(),
+            other => value_to_bytes("io.write", other)?,
+        };
+        with_file("io.write", &args[0], |handle| handle.write(&bytes))?;
+        Ok(Value::Boolean(true))
+    }));
+
+    module.insert("seek".to_string(), Value::NativeFunction(|args| {
+        if args.len() < 2 || args.len() > 3 { return Err("io.seek espera 2 ou 3 argumentos".to_string()); }
+        
+        let offset = match &args[1] {
+            Value::Number(n) if n.fract() == 0.0 => *n as i64,
+            _ => return Err("io.seek espera um deslocamento inteiro".to_string()),
+        };
+        let from_start = || u64::try_from(offset)
+            .map(SeekFrom::Start)
+            .map_err(|_| "io.seek: deslocamento negativo a partir do início do arquivo".to_string());
+        let position = match args.get(2) {
+            None | Some(Value::Nil) => from_start()?,
+            Some(Value::String(whence)) => match whence.as_str() {
+                "start" => from_start()?,
+                "current" => SeekFrom::Current(offset),
+                "end" => SeekFrom::End(offset),
+                _ => return Err("io.seek espera \"start\", \"current\" ou \"end\" como origem".to_string()),
+            },
+            Some(_) => return Err("io.seek espera \"start\", \"current\" ou \"end\" como origem".to_string()),
+        };
+        let new_position = with_file("io.seek", &args[0], |handle| handle.seek(position))?;
+        Ok(Value::Number(new_position as f64))
+    }));
+
+    module.insert("tell".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.tell espera 1 argumento".to_string()); }
+        
+        let position = with_file("io.tell", &args[0], |handle| handle.position())?;
+        Ok(Value::Number(position as f64))
+    }));
+
+    module.insert("flush".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.flush espera 1 argumento".to_string()); }
+        
+        with_file("io.flush", &args[0], |handle| handle.flush())?;
+        Ok(Value::Boolean(true))
+    }));
+
+    module.insert("close".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.close espera 1 argumento".to_string()); }
+        
+        let handle = resource(&args[0], "file")
+            .ok_or_else(|| "io.close espera um arquivo aberto com io.open".to_string())?;
+        // Fechar duas vezes não é erro; retorna false se já estava fechado.
+        // Sem io.close o arquivo é fechado quando deixa de ser referenciado.
+        if let Ok(result) = handle.with("io.close", |handle: &mut FileHandle| handle.flush()) {
+            result.map_err(|e| format!("Erro ao gravar arquivo: {}", e))?;
+        }
+        Ok(Value::Boolean(handle.release("io.close")?))
+    }));
+    
+    module.insert("walk".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.walk espera 1 ou 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(path) => {
+                let options = WalkOptions::parse(args.get(1))?;
+                let filesystem = vfs::current();
+                let mut entries = Vec::new();
+                let mut visited = std::collections::HashSet::new();
+                if options.follow_symlinks {
+                    if let Ok(canonical) = filesystem.canonicalize(Path::new(path)) {
+                        visited.insert(canonical);
+                    }
+                }
+                walk_dir(&*filesystem, Path::new(path), Path::new(""), 1, &options, &mut visited, &mut entries)?;
+                Ok(Value::List(entries))
+            },
+            _ => Err("io.walk espera uma string (caminho do diretório)".to_string()),
+        }
+    }));
+    
+    module.insert("stdin_read_line".to_string(), Value::NativeFunction(|args| {
+        if !args.is_empty() { return Err("io.stdin_read_line não espera argumentos".to_string()); }
+        
+        stdin_line("io.stdin_read_line")
+    }));
+    
+    module.insert("stdin_lines".to_string(), Value::NativeFunction(|args| {
+        if !args.is_empty() { return Err("io.stdin_lines não espera argumentos".to_string()); }
+        
+        // Iterador preguiçoso: cada item lê a próxima linha da entrada padrão.
+        Ok(collections::new_generator(|_state| {
+            match stdin_line("io.stdin_lines")? {
+                Value::Nil => Ok(Value::Nil),
+                line => Ok(Value::List(vec![line])),
+            }
+        }, Value::Nil))
+    }));
+    
+    module.insert("stdin_read_all".to_string(), Value::NativeFunction(|args| {
+        if !args.is_empty() { return Err("io.stdin_read_all não espera argumentos".to_string()); }
+        
+        let mut content = String::new();
+        match std::io::stdin().lock().read_to_string(&mut content) {
+            Ok(_) => Ok(Value::String(content)),
+            Err(e) => Err(format!("Erro ao ler a entrada padrão: {}", e)),
+        }
+    }));
+    
+    module.insert("input".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 1 { return Err("io.input espera 0 ou 1 argumento".to_string()); }
+        
+        if let Some(prompt) = args.first() {
+            let mut stdout = std::io::stdout().lock();
+            write!(stdout, "{}", prompt)
+                .and_then(|_| stdout.flush())
+                .map_err(|e| format!("Erro ao gravar na saída padrão: {}", e))?;
+        }
+        stdin_line("io.input")
+    }));
+    
+    module.insert("eprint".to_string(), Value::NativeFunction(|args| {
+        write_stderr(&args, false)
+    }));
+    
+    module.insert("eprintln".to_string(), Value::NativeFunction(|args| {
+        write_stderr(&args, true)
+    }));
+    
+    module.insert("flush_stdout".to_string(), Value::NativeFunction(|args| {
+        if !args.is_empty() { return Err("io.flush_stdout não espera argumentos".to_string()); }
+        
+        std::io::stdout().flush()
+            .map_err(|e| format!("Erro ao gravar na saída padrão: {}", e))?;
+        Ok(Value::Boolean(true))
+    }));
+    
+    module.insert("is_tty".to_string(), Value::NativeFunction(|args| {
+        if args.len() > 1 { return Err("io.is_tty espera 0 ou 1 argumento".to_string()); }
+        
+        use std::io::IsTerminal;
+        let stream = match args.first() {
+            None | Some(Value::Nil) => "stdout",
+            Some(Value::String(stream)) => stream.as_str(),
+            Some(_) => return Err("io.is_tty espera \"stdin\", \"stdout\" ou \"stderr\"".to_string()),
+        };
+        match stream {
+            "stdin" => Ok(Value::Boolean(std::io::stdin().is_terminal())),
+            "stdout" => Ok(Value::Boolean(std::io::stdout().is_terminal())),
+            "stderr" => Ok(Value::Boolean(std::io::stderr().is_terminal())),
+            other => Err(format!("io.is_tty: fluxo desconhecido '{}' (use \"stdin\", \"stdout\" ou \"stderr\")", other)),
+        }
+    }));
+    
+    module.insert("watch".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.watch espera 1 ou 2 argumentos".to_string()); }
+        
+        let roots = match &args[0] {
+            Value::String(path) => vec![PathBuf::from(path)],
+            Value::List(paths) => paths.iter().map(|path| match path {
+                Value::String(path) => Ok(PathBuf::from(path)),
+                _ => Err("io.watch espera uma lista de strings (caminhos)".to_string()),
+            }).collect::<Result<_, _>>()?,
+            _ => return Err("io.watch espera uma string ou lista de strings (caminhos)".to_string()),
+        };
+        let options = WatchOptions::parse(args.get(1))?;
+        let mut watcher = Watcher::new(&roots, &options)?;
+
+        match &options.callback {
+            // Com callback, bloqueia até o callback retornar false ou o timeout expirar;
+            // sem a opção timeout isso pode ser para sempre.
+            Some(callback) => {
+                while let Some(event) = watcher.next_event()? {
+                    if collections::call_function(callback, vec![event])? == Value::Boolean(false) {
+                        break;
+                    }
+                }
+                Ok(Value::Nil)
+            },
+            None => {
+                // O observador vive enquanto o iterador existir; depois de
+                // io.unwatch o iterador simplesmente termina. Sem a opção timeout,
+                // pedir o próximo item bloqueia até algo mudar.
+                let state = track("watcher", Vec::new(), watcher);
+                Ok(collections::new_generator(|state| {
+                    let event = match resource(&state[0], "watcher") {
+                        Some(handle) => handle.with("io.watch", |watcher: &mut Watcher| watcher.next_event())
+                            .unwrap_or(Ok(None))?,
+                        None => None,
+                    };
+                    Ok(event.map_or(Value::Nil, |event| Value::List(vec![event])))
+                }, state))
+            },
+        }
+    }));
+    
+    module.insert("unwatch".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 1 { return Err("io.unwatch espera 1 argumento".to_string()); }
+        
+        let handle = collections::object_field(&args[0], "state")
+            .and_then(|state| resource(state, "watcher"))
+            .ok_or_else(|| "io.unwatch espera um iterador retornado por io.watch".to_string())?;
+        Ok(Value::Boolean(handle.release("io.unwatch")?))
+    }));
+    
+    module.insert("glob".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() || args.len() > 2 { return Err("io.glob espera 1 ou 2 argumentos".to_string()); }
+        
+        match &args[0] {
+            Value::String(pattern) => {
+                let options = glob::GlobOptions::parse("io.glob", args.get(1))?;
+                let paths = glob::expand(pattern, &options)?;
+                Ok(Value::List(paths.into_iter().map(Value::String).collect()))
+            },
+            _ => Err("io.glob espera uma string (padrão)".to_string()),
+        }
+    }));
+    
     let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
     Value::Dict(dict_map)
 }
+
+/// Próxima linha da entrada padrão, ou nil no fim (ex.: fim de um pipe ou Ctrl-D).
+fn stdin_line(name: &str) -> Result<Value, String> {
+    match read_raw_line(&mut std::io::stdin().lock()) {
+        Ok(Some(line)) => String::from_utf8(line)
+            .map(Value::String)
+            .map_err(|_| format!("{}: linha da entrada padrão não é UTF-8 válido", name)),
+        Ok(None) => Ok(Value::Nil),
+        Err(e) => Err(format!("{}: erro ao ler a entrada padrão: {}", name, e)),
+    }
+}
+
+/// `io.eprint`/`io.eprintln`: valores separados por espaço na saída de erro.
+fn write_stderr(args: &[Value], newline: bool) -> Result<Value, String> {
+    let mut text = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(" ");
+    if newline {
+        text.push('\n');
+    }
+    std::io::stderr().lock().write_all(text.as_bytes())
+        .map_err(|e| format!("Erro ao gravar na saída de erro: {}", e))?;
+    Ok(Value::Nil)
+}
+
+/// Lê uma linha sem o `\n` (ou `\r\n`) final; `None` no fim da entrada.
+fn read_raw_line(reader: &mut impl BufRead) -> std::io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+        return Ok(None);
+    }
+    if line.ends_with(b"\n") {
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+    }
+    Ok(Some(line))
+}
+
+/// Arquivo aberto com `io.open`. Leituras usam um `BufReader` e escritas um
+/// `BufWriter`; ao alternar entre os dois o buffer é descarregado e a posição
+/// lógica é preservada. Ao ser descartado, o `BufWriter` grava o que falta e o
+/// arquivo é fechado.
+struct FileHandle {
+    stream: Option<Stream>,
+    path: String,
+    mode: String,
+    readable: bool,
+    writable: bool,
+    binary: bool,
+}
+
+enum Stream {
+    Reader(BufReader<Box<dyn FileStream>>),
+    Writer(BufWriter<Box<dyn FileStream>>),
+}
+
+impl FileHandle {
+    fn reader(&mut self) -> std::io::Result<&mut BufReader<Box<dyn FileStream>>> {
+        if !self.readable {
+            return Err(std::io::Error::other("arquivo não foi aberto para leitura"));
+        }
+        if matches!(self.stream, Some(Stream::Writer(_))) {
+            let Some(Stream::Writer(writer)) = self.stream.take() else { unreachable!() };
+            match writer.into_inner() {
+                Ok(file) => self.stream = Some(Stream::Reader(BufReader::new(file))),
+                Err(e) => {
+                    let error = std::io::Error::new(e.error().kind(), e.error().to_string());
+                    self.stream = Some(Stream::Writer(e.into_inner()));
+                    return Err(error);
+                },
+            }
+        }
+        match &mut self.stream {
+            Some(Stream::Reader(reader)) => Ok(reader),
+            _ => Err(std::io::Error::other("arquivo fechado")),
+        }
+    }
+
+    fn writer(&mut self) -> std::io::Result<&mut BufWriter<Box<dyn FileStream>>> {
+        if !self.writable {
+            return Err(std::io::Error::other("arquivo não foi aberto para escrita"));
+        }
+        if matches!(self.stream, Some(Stream::Reader(_))) {
+            let Some(Stream::Reader(mut reader)) = self.stream.take() else { unreachable!() };
+            // O BufReader pode ter lido além da posição lógica; volta para ela.
+            let position = reader.stream_position();
+            let mut file = reader.into_inner();
+            let result = position.and_then(|position| file.seek(SeekFrom::Start(position)));
+            self.stream = Some(Stream::Writer(BufWriter::new(file)));
+            result?;
+        }
+        match &mut self.stream {
+            Some(Stream::Writer(writer)) => Ok(writer),
+            _ => Err(std::io::Error::other("arquivo fechado")),
+        }
+    }
+
+    /// Lê até `limit` bytes (ou até o fim). Com `whole_chars`, continua lendo
+    /// alguns bytes se o corte cair no meio de um caractere UTF-8.
+    fn read(&mut self, limit: Option<u64>, whole_chars: bool) -> std::io::Result<Vec<u8>> {
+        let reader = self.reader()?;
+        let mut buffer = Vec::new();
+        match limit {
+            Some(limit) => { reader.by_ref().take(limit).read_to_end(&mut buffer)?; },
+            None => { reader.read_to_end(&mut buffer)?; },
+        }
+        if whole_chars {
+            let mut extra = 0;
+            while extra < 3 && matches!(std::str::from_utf8(&buffer), Err(e) if e.error_len().is_none()) {
+                let mut byte = [0u8; 1];
+                if reader.read(&mut byte)? == 0 {
+                    break;
+                }
+                buffer.push(byte[0]);
+                extra += 1;
+            }
+        }
+        Ok(buffer)
+    }
+
+    /// Próxima linha sem o `\n` (ou `\r\n`) final; `None` no fim do arquivo.
+    fn read_line(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        read_raw_line(self.reader()?)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.writer()?.write_all(bytes)
+    }
+
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        match &mut self.stream {
+            Some(Stream::Reader(reader)) => reader.seek(position),
+            Some(Stream::Writer(writer)) => writer.seek(position),
+            None => Err(std::io::Error::other("arquivo fechado")),
+        }
+    }
+
+    /// Posição lógica atual (descontando o que está nos buffers).
+    fn position(&mut self) -> std::io::Result<u64> {
+        match &mut self.stream {
+            Some(Stream::Reader(reader)) => reader.stream_position(),
+            Some(Stream::Writer(writer)) => writer.stream_position(),
+            None => Err(std::io::Error::other("arquivo fechado")),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.stream {
+            Some(Stream::Writer(writer)) => writer.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl NativeObject for FileHandle {
+    fn describe(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<file {} ({})>", self.path, self.mode)
+    }
+}
+
+/// Executa `f` sobre o arquivo referenciado pelo handle, convertendo erros de I/O.
+fn with_file<R>(
+    name: &str,
+    value: &Value,
+    f: impl FnOnce(&mut FileHandle) -> std::io::Result<R>,
+) -> Result<R, String> {
+    match resource(value, "file") {
+        Some(handle) => handle.with(name, f)?.map_err(|e| format!("{}: {}", name, e)),
+        None => Err(format!("{} espera um arquivo aberto com io.open", name)),
+    }
+}
+
+/// Lê a próxima linha como texto (ou bytes, no modo binário); `nil` no fim do arquivo.
+fn read_line(name: &str, value: &Value) -> Result<Value, String> {
+    let (line, binary) = with_file(name, value, |handle| {
+        let binary = handle.binary;
+        handle.read_line().map(|line| (line, binary))
+    })?;
+    match line {
+        None => Ok(Value::Nil),
+        Some(line) if binary => Ok(bytes_to_value(&line)),
+        Some(line) => String::from_utf8(line)
+            .map(Value::String)
+            .map_err(|_| format!("{}: linha não é UTF-8 válido (abra com o modo \"rb\")", name)),
+    }
+}
+
+/// Opções de `io.walk`. Padrões sem `/` casam com o nome da entrada; com `/`,
+/// com o caminho relativo ao diretório inicial, na sintaxe do módulo `glob`.
+struct WalkOptions {
+    /// Profundidade máxima (1 = só o próprio diretório); padrão sem limite.
+    max_depth: Option<usize>,
+    /// Entra em links para diretórios.
+    follow_symlinks: bool,
+    /// Ignora entradas que começam com `.`.
+    skip_hidden: bool,
+    /// Só entradas que casam com algum padrão são retornadas (diretórios continuam sendo percorridos).
+    include: Vec<String>,
+    /// Entradas que casam são ignoradas e não são percorridas.
+    exclude: Vec<String>,
+}
+
+impl WalkOptions {
+    fn parse(options: Option<&Value>) -> Result<Self, String> {
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(WalkOptions {
+                max_depth: None, follow_symlinks: false, skip_hidden: false, include: Vec::new(), exclude: Vec::new(),
+            }),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err("io.walk espera um dicionário de opções".to_string()),
+        };
+        let field = |key: &str| map.get(&Value::String(key.to_string()));
+        let flag = |key: &str| match field(key) {
+            None | Some(Value::Nil) => Ok(false),
+            Some(Value::Boolean(b)) => Ok(*b),
+            Some(_) => Err(format!("io.walk espera um booleano na opção '{}'", key)),
+        };
+        let patterns = |key: &str| match field(key) {
+            None | Some(Value::Nil) => Ok(Vec::new()),
+            Some(Value::String(pattern)) => Ok(vec![pattern.clone()]),
+            Some(Value::List(list)) => list.iter().map(|item| match item {
+                Value::String(pattern) => Ok(pattern.clone()),
+                _ => Err(format!("io.walk espera strings na opção '{}'", key)),
+            }).collect(),
+            Some(_) => Err(format!("io.walk espera uma string ou lista de strings na opção '{}'", key)),
+        };
+        let max_depth = match field("max_depth") {
+            None | Some(Value::Nil) => None,
+            Some(Value::Number(n)) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+            Some(_) => return Err("io.walk espera um número inteiro não negativo em 'max_depth'".to_string()),
+        };
+
+        Ok(WalkOptions {
+            max_depth,
+            follow_symlinks: flag("follow_symlinks")?,
+            skip_hidden: flag("skip_hidden")?,
+            include: patterns("include")?,
+            exclude: patterns("exclude")?,
+        })
+    }
+
+    fn matches_any(patterns: &[String], name: &str, relative: &str) -> Result<bool, String> {
+        // Entradas ocultas são tratadas por `skip_hidden`, então curingas casam com elas.
+        let glob_options = glob::GlobOptions { case_insensitive: false, dot: true };
+        for pattern in patterns {
+            let target = if pattern.contains('/') { relative } else { name };
+            if glob::matches(pattern, target, &glob_options)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Dicionário com chaves string, usado nos resultados de `walk`, `stat` etc.
+fn record(fields: Vec<(&str, Value)>) -> Value {
+    Value::Dict(fields.into_iter().map(|(k, v)| (Value::String(k.to_string()), v)).collect())
+}
+
+/// Tipo de uma entrada: "file", "dir", "symlink" ou "other".
+fn file_kind(info: &FileInfo) -> &'static str {
+    match info.kind {
+        FileKind::Dir => "dir",
+        FileKind::File => "file",
+        FileKind::Symlink => "symlink",
+        FileKind::Other => "other",
+    }
+}
+
+/// Datas são segundos (com fração) desde 1970, como em `sys.time`.
+fn time_to_value(time: Option<SystemTime>) -> Value {
+    match time {
+        Some(time) => match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => Value::Number(since.as_secs_f64()),
+            Err(before) => Value::Number(-before.duration().as_secs_f64()),
+        },
+        None => Value::Nil,
+    }
+}
+
+fn value_to_time(name: &str, value: &Value) -> Result<SystemTime, String> {
+    match value {
+        Value::Number(secs) if secs.is_finite() => {
+            let offset = Duration::try_from_secs_f64(secs.abs())
+                .map_err(|_| format!("{} recebeu uma data fora do intervalo", name))?;
+            let time = if *secs >= 0.0 { UNIX_EPOCH.checked_add(offset) } else { UNIX_EPOCH.checked_sub(offset) };
+            time.ok_or_else(|| format!("{} recebeu uma data fora do intervalo", name))
+        },
+        _ => Err(format!("{} espera um número (segundos desde 1970)", name)),
+    }
+}
+
+/// Resultado de `io.stat`/`io.lstat`. `ctime` é a última mudança de status no Unix
+/// e `created` a data de criação; campos que a plataforma ou o backend (sem disco
+/// por trás, `host` é `None`) não fornecem ficam nil.
+fn metadata_record(path: &str, info: &FileInfo, is_symlink: bool, host: Option<&Metadata>) -> Result<Value, String> {
+    let mut fields = vec![
+        ("path", Value::String(path.to_string())),
+        ("type", Value::String(file_kind(info).to_string())),
+        ("size", Value::Number(info.len as f64)),
+        ("mtime", time_to_value(info.modified)),
+        ("atime", time_to_value(info.accessed)),
+        ("created", time_to_value(host.and_then(|metadata| metadata.created().ok()))),
+        ("readonly", Value::Boolean(info.readonly)),
+        ("is_symlink", Value::Boolean(is_symlink)),
+    ];
+
+    #[cfg(unix)]
+    let os_fields = host.map(|metadata| {
+        use std::os::unix::fs::MetadataExt;
+        let ctime = metadata.ctime() as f64 + metadata.ctime_nsec() as f64 / 1e9;
+        [
+            ("ctime", Value::Number(ctime)),
+            ("permissions", Value::Number((metadata.mode() & 0o7777) as f64)),
+            ("uid", Value::Number(metadata.uid() as f64)),
+            ("gid", Value::Number(metadata.gid() as f64)),
+            ("inode", Value::Number(metadata.ino() as f64)),
+            ("device", Value::Number(metadata.dev() as f64)),
+            ("nlink", Value::Number(metadata.nlink() as f64)),
+        ]
+    });
+    #[cfg(not(unix))]
+    let os_fields: Option<[(&str, Value); 7]> = host.and(None);
+    fields.extend(os_fields.unwrap_or_else(|| {
+        ["ctime", "permissions", "uid", "gid", "inode", "device", "nlink"].map(|key| (key, Value::Nil))
+    }));
+
+    Ok(record(fields))
+}
+
+/// Percorre `dir` em pré-ordem, com as entradas de cada diretório em ordem alfabética.
+/// Cada entrada é um dicionário com `path`, `name`, `type` ("file", "dir",
+/// "symlink" ou "other"), `symlink` e `depth`. Links que o backend não deixa
+/// seguir (ex.: para fora da raiz de um `ChrootFs`) aparecem como "symlink".
+fn walk_dir(
+    filesystem: &dyn FileSystem,
+    dir: &Path,
+    relative_dir: &Path,
+    depth: usize,
+    options: &WalkOptions,
+    visited: &mut std::collections::HashSet<std::path::PathBuf>,
+    out: &mut Vec<Value>,
+) -> Result<(), String> {
+    if options.max_depth.is_some_and(|max| depth > max) {
+        return Ok(());
+    }
+
+    let children = filesystem.read_dir(dir)
+        .map_err(|e| format!("Erro ao ler diretório {}: {}", dir.display(), e))?;
+
+    for file_name in children {
+        let path = dir.join(&file_name);
+        let name = path_to_string(Path::new(&file_name))?;
+        let relative = relative_dir.join(&name);
+        let relative_str = path_to_string(&relative)?;
+
+        if options.skip_hidden && name.starts_with('.') {
+            continue;
+        }
+        if WalkOptions::matches_any(&options.exclude, &name, &relative_str)? {
+            continue;
+        }
+
+        let link_info = filesystem.symlink_metadata(&path)
+            .map_err(|e| format!("Erro ao ler {}: {}", path.display(), e))?;
+        let is_symlink = link_info.is_symlink();
+        // Links quebrados continuam aparecendo como "symlink".
+        let info = if is_symlink && options.follow_symlinks {
+            filesystem.metadata(&path).unwrap_or(link_info)
+        } else {
+            link_info
+        };
+        let kind = file_kind(&info);
+
+        if options.include.is_empty() || WalkOptions::matches_any(&options.include, &name, &relative_str)? {
+            out.push(record(vec![
+                ("path", Value::String(path_to_string(&path)?)),
+                ("name", Value::String(name.clone())),
+                ("type", Value::String(kind.to_string())),
+                ("symlink", Value::Boolean(is_symlink)),
+                ("depth", Value::Number(depth as f64)),
+            ]));
+        }
+
+        if kind == "dir" {
+            // Seguindo links, o mesmo diretório pode ser alcançado de novo (ou em ciclo).
+            if options.follow_symlinks {
+                let first_visit = filesystem.canonicalize(&path).is_ok_and(|canonical| visited.insert(canonical));
+                if !first_visit {
+                    continue;
+                }
+            }
+            walk_dir(filesystem, &path, &relative, depth + 1, options, visited, out)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Conflict {
+    Error,
+    Overwrite,
+    Skip,
+}
+
+/// Opções de `io.copy` e `io.move` (`preserve` liga as duas opções de preservação).
+struct CopyOptions {
+    /// Permite copiar diretórios.
+    recursive: bool,
+    /// `on_conflict`: com "overwrite" e "skip", diretórios existentes são mesclados arquivo a arquivo.
+    conflict: Conflict,
+    preserve_permissions: bool,
+    preserve_timestamps: bool,
+}
+
+impl CopyOptions {
+    fn parse(name: &str, options: Option<&Value>) -> Result<Self, String> {
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(CopyOptions {
+                recursive: false, conflict: Conflict::Error, preserve_permissions: false, preserve_timestamps: false,
+            }),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err(format!("{} espera um dicionário de opções", name)),
+        };
+        let field = |key: &str| map.get(&Value::String(key.to_string()));
+        let flag = |key: &str, default: bool| match field(key) {
+            None | Some(Value::Nil) => Ok(default),
+            Some(Value::Boolean(b)) => Ok(*b),
+            Some(_) => Err(format!("{} espera um booleano na opção '{}'", name, key)),
+        };
+        let conflict = match field("on_conflict") {
+            None | Some(Value::Nil) => Conflict::Error,
+            Some(Value::String(policy)) => match policy.as_str() {
+                "error" => Conflict::Error,
+                "overwrite" => Conflict::Overwrite,
+                "skip" => Conflict::Skip,
+                other => return Err(format!("{}: política de conflito desconhecida '{}' (use \"error\", \"overwrite\" ou \"skip\")", name, other)),
+            },
+            Some(_) => return Err(format!("{} espera uma string na opção 'on_conflict'", name)),
+        };
+        let preserve = flag("preserve", false)?;
+
+        Ok(CopyOptions {
+            recursive: flag("recursive", false)?,
+            conflict,
+            preserve_permissions: flag("preserve_permissions", preserve)?,
+            preserve_timestamps: flag("preserve_timestamps", preserve)?,
+        })
+    }
+}
+
+/// Se `dst` já é o próprio `src` (`a` e `./a`, um link físico, um diretório
+/// alcançado por um link...), caso em que substituir o destino apagaria a origem.
+/// Com `follow_src`, um link simbólico em `src` é comparado pelo seu alvo. O
+/// destino nunca é seguido: um link ali é removido, não escrito por cima.
+fn same_file(filesystem: &dyn FileSystem, src: &Path, dst: &Path, follow_src: bool) -> bool {
+    let src_info = if follow_src { filesystem.metadata(src) } else { filesystem.symlink_metadata(src) };
+    let (Ok(src_info), Ok(dst_info)) = (src_info, filesystem.symlink_metadata(dst)) else {
+        return false;
+    };
+    if let (Some(src_id), Some(dst_id)) = (src_info.id, dst_info.id) {
+        return src_id == dst_id;
+    }
+    // Sem identidade de disco, compara os caminhos canônicos; o último
+    // componente fica de fora da resolução para não seguir links.
+    let without_following = |path: &Path| match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => {
+            let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+            filesystem.canonicalize(parent).map(|parent| parent.join(file_name))
+        },
+        _ => filesystem.canonicalize(path),
+    };
+    let src_real = if follow_src { filesystem.canonicalize(src) } else { without_following(src) };
+    matches!((src_real, without_following(dst)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Copia `src` para `dst` e retorna quantos arquivos foram copiados. Links simbólicos
+/// dentro de diretórios são recriados como links, não seguidos.
+fn copy_entry(filesystem: &dyn FileSystem, src: &Path, dst: &Path, info: &FileInfo, options: &CopyOptions) -> Result<usize, String> {
+    let existing = filesystem.symlink_metadata(dst).ok();
+    let error = |e: std::io::Error| format!("Erro ao copiar {} para {}: {}", src.display(), dst.display(), e);
+
+    let mut copied = 0;
+    if info.is_dir() {
+        match &existing {
+            Some(existing) if existing.is_dir() => {},
+            Some(_) if options.conflict == Conflict::Skip => return Ok(0),
+            Some(_) => return Err(format!("io.copy: destino existe e não é um diretório: {}", dst.display())),
+            None => filesystem.create_dir(dst).map_err(error)?,
+        }
+        for name in filesystem.read_dir(src).map_err(error)? {
+            let child = src.join(&name);
+            let child_info = filesystem.symlink_metadata(&child).map_err(error)?;
+            copied += copy_entry(filesystem, &child, &dst.join(&name), &child_info, options)?;
+        }
+    } else {
+        match &existing {
+            Some(existing) if existing.is_dir() => {
+                return Err(format!("io.copy: não é possível substituir o diretório {} por um arquivo", dst.display()));
+            },
+            Some(_) if options.conflict == Conflict::Skip => return Ok(0),
+            // Remove antes para não escrever através de um link no destino.
+            Some(_) => filesystem.remove_file(dst).map_err(error)?,
+            None => {},
+        }
+        if info.is_symlink() {
+            filesystem.read_link(src)
+                .and_then(|target| filesystem.symlink(&target, dst))
+                .map_err(error)?;
+            return Ok(1);
+        }
+        filesystem.copy_file(src, dst).map_err(error)?;
+        copied = 1;
+    }
+
+    // Diretórios recebem permissões e datas depois do conteúdo, senão a cópia
+    // dos filhos alteraria o mtime (ou falharia num diretório somente leitura).
+    if options.preserve_timestamps {
+        if let Some(modified) = info.modified {
+            filesystem.set_times(dst, modified, info.accessed).map_err(error)?;
+        }
+    }
+    if options.preserve_permissions {
+        filesystem.copy_permissions(src, dst).map_err(error)?;
+    }
+    Ok(copied)
+}
+
+/// `io.move`/`io.rename`: tenta `rename`; entre sistemas de arquivos diferentes,
+/// copia (preservando permissões e datas) e remove a origem.
+fn move_path(name: &str, args: &[Value]) -> Result<Value, String> {
+    if args.len() < 2 || args.len() > 3 { return Err(format!("{} espera 2 ou 3 argumentos", name)); }
+
+    let (src, dst) = match (&args[0], &args[1]) {
+        (Value::String(src), Value::String(dst)) => (Path::new(src), Path::new(dst)),
+        _ => return Err(format!("{} espera duas strings (origem e destino)", name)),
+    };
+    let options = CopyOptions::parse(name, args.get(2))?;
+    let filesystem = vfs::current();
+    let info = filesystem.symlink_metadata(src).map_err(|e| format!("Erro ao ler {}: {}", src.display(), e))?;
+    if same_file(&*filesystem, src, dst, false) {
+        return Err(format!("{}: origem e destino são o mesmo arquivo: {}", name, dst.display()));
+    }
+
+    if let Ok(existing) = filesystem.symlink_metadata(dst) {
+        match options.conflict {
+            Conflict::Error => return Err(format!("{}: destino já existe: {}", name, dst.display())),
+            Conflict::Skip => return Ok(Value::Boolean(false)),
+            Conflict::Overwrite if existing.is_dir() && !info.is_dir() => {
+                return Err(format!("{}: não é possível substituir o diretório {} por um arquivo", name, dst.display()));
+            },
+            Conflict::Overwrite => {},
+        }
+    }
+
+    match filesystem.rename(src, dst) {
+        Ok(_) => Ok(Value::Boolean(true)),
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            let options = CopyOptions { recursive: true, preserve_permissions: true, preserve_timestamps: true, ..options };
+            copy_entry(&*filesystem, src, dst, &info, &options)?;
+            let removed = if info.is_dir() { filesystem.remove_dir_all(src) } else { filesystem.remove_file(src) };
+            removed.map_err(|e| format!("{}: cópia feita, mas erro ao remover {}: {}", name, src.display(), e))?;
+            Ok(Value::Boolean(true))
+        },
+        Err(e) => Err(format!("Erro ao mover {} para {}: {}", src.display(), dst.display(), e)),
+    }
+}
+
+/// Escreve num arquivo temporário ao lado de `path`, faz fsync e renomeia por cima
+/// do destino: leitores veem o conteúdo antigo ou o novo, nunca um arquivo truncado.
+fn write_atomic(filesystem: &dyn FileSystem, path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "caminho sem nome de arquivo"))?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let create_new = OpenMode { write: true, create_new: true, ..OpenMode::default() };
+    let mut attempt = 0;
+    let (temp_path, mut temp) = loop {
+        let mut temp_name = std::ffi::OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".tmp-{}-{}", std::process::id(), attempt));
+        let temp_path = dir.join(temp_name);
+        match filesystem.open(&temp_path, create_new) {
+            Ok(file) => break (temp_path, file),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    };
+
+    let result = (|| {
+        temp.write_all(bytes)?;
+        temp.sync()?;
+        drop(temp);
+        // Mantém as permissões de um arquivo já existente.
+        if filesystem.metadata(path).is_ok() {
+            filesystem.copy_permissions(path, &temp_path)?;
+        }
+        filesystem.rename_atomic(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = filesystem.remove_file(&temp_path);
+    }
+    result
+}
+
+/// `io.lock(path, {shared})` espera a trava; `io.try_lock` retorna nil se ela
+/// estiver ocupada. As travas são consultivas (só valem entre quem também usa lock)
+/// e ficam ativas até `io.unlock` ou até a trava deixar de ser referenciada.
+fn lock_path(name: &str, args: &[Value], wait: bool) -> Result<Value, String> {
+    if args.is_empty() || args.len() > 2 { return Err(format!("{} espera 1 ou 2 argumentos", name)); }
+
+    let path = match &args[0] {
+        Value::String(path) => path,
+        _ => return Err(format!("{} espera uma string (caminho)", name)),
+    };
+    let shared = match args.get(1) {
+        None | Some(Value::Nil) => false,
+        Some(Value::Dict(map)) => match map.get(&Value::String("shared".to_string())) {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err(format!("{} espera um booleano na opção 'shared'", name)),
+        },
+        Some(_) => return Err(format!("{} espera um dicionário de opções", name)),
+    };
+
+    let guard = match vfs::current().lock(Path::new(path), shared, wait) {
+        Ok(guard) => guard,
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(Value::Nil),
+        Err(e) => return Err(format!("Erro ao travar {}: {}", path, e)),
+    };
+
+    Ok(track("lock", vec![
+        ("path", Value::String(path.clone())),
+        ("shared", Value::Boolean(shared)),
+    ], FileLock { guard, path: path.clone(), shared }))
+}
+
+/// Trava obtida com `io.lock`; é liberada ao ser descartada.
+struct FileLock {
+    guard: Box<dyn LockGuard>,
+    path: String,
+    shared: bool,
+}
+
+impl NativeObject for FileLock {
+    fn describe(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mode = if self.shared { "compartilhada" } else { "exclusiva" };
+        write!(f, "<lock {} ({})>", self.path, mode)
+    }
+}
+
+/// Opções de `io.delete`.
+struct DeleteOptions {
+    /// Permite apagar diretórios não vazios.
+    recursive: bool,
+    /// Não apaga nada e retorna a lista de caminhos que seriam removidos.
+    dry_run: bool,
+    /// Move para a lixeira do usuário (XDG) em vez de apagar.
+    trash: bool,
+}
+
+impl DeleteOptions {
+    fn parse(options: Option<&Value>) -> Result<Self, String> {
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(DeleteOptions { recursive: false, dry_run: false, trash: false }),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err("io.delete espera um dicionário de opções".to_string()),
+        };
+        let flag = |key: &str| match map.get(&Value::String(key.to_string())) {
+            None | Some(Value::Nil) => Ok(false),
+            Some(Value::Boolean(b)) => Ok(*b),
+            Some(_) => Err(format!("io.delete espera um booleano na opção '{}'", key)),
+        };
+        Ok(DeleteOptions { recursive: flag("recursive")?, dry_run: flag("dry_run")?, trash: flag("trash")? })
+    }
+}
+
+/// Recusa apagar `/`, a pasta pessoal, o diretório atual (ou algo que os contenha)
+/// e diretórios não vazios sem `recursive`.
+fn check_deletable(filesystem: &dyn FileSystem, path: &Path, info: &FileInfo, recursive: bool) -> Result<(), String> {
+    if info.is_symlink() {
+        return Ok(());
+    }
+
+    let refuse = || format!("io.delete: recusando apagar {} (raiz, pasta pessoal ou diretório atual)", path.display());
+    match filesystem.host_path(path) {
+        Some(host) => {
+            let target = fs::canonicalize(host).map_err(|e| format!("Erro ao deletar: {}", e))?;
+            let protected = [home_dir(), std::env::current_dir().ok(), filesystem.host_path(Path::new("/"))];
+            if target.parent().is_none()
+                || protected.iter().flatten().filter_map(|dir| fs::canonicalize(dir).ok()).any(|dir| dir.starts_with(&target))
+            {
+                return Err(refuse());
+            }
+        },
+        // Sem disco por trás, só a raiz virtual é protegida.
+        None if !path.components().any(|c| matches!(c, std::path::Component::Normal(_))) => return Err(refuse()),
+        None => {},
+    }
+
+    if info.is_dir() && !recursive {
+        let entries = filesystem.read_dir(path).map_err(|e| format!("Erro ao deletar: {}", e))?;
+        if !entries.is_empty() {
+            return Err(format!("io.delete: diretório não está vazio: {} (use a opção recursive)", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Caminhos que `io.delete` removeria, na ordem de remoção (conteúdo antes do diretório).
+fn deletion_list(filesystem: &dyn FileSystem, path: &Path, info: &FileInfo, out: &mut Vec<Value>) -> Result<(), String> {
+    if info.is_dir() {
+        let names = filesystem.read_dir(path)
+            .map_err(|e| format!("Erro ao ler diretório {}: {}", path.display(), e))?;
+        for name in names {
+            let child = path.join(name);
+            let child_info = filesystem.symlink_metadata(&child)
+                .map_err(|e| format!("Erro ao ler {}: {}", child.display(), e))?;
+            deletion_list(filesystem, &child, &child_info, out)?;
+        }
+    }
+    out.push(Value::String(path_to_string(path)?));
+    Ok(())
+}
+
+/// Move `path` para a lixeira XDG (`$XDG_DATA_HOME/Trash`, normalmente
+/// `~/.local/share/Trash`), gravando o `.trashinfo` usado para restaurar o arquivo.
+fn move_to_trash(path: &Path) -> std::io::Result<()> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| home_dir().map(|home| home.join(".local/share")))
+        .ok_or_else(|| std::io::Error::other("não foi possível encontrar a lixeira (HOME não definido)"))?;
+    let trash = data_home.join("Trash");
+    let (files_dir, info_dir) = (trash.join("files"), trash.join("info"));
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let original = std::path::absolute(path)?;
+    let name = original.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "caminho sem nome de arquivo"))?
+        .to_string_lossy()
+        .into_owned();
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&original.to_string_lossy()),
+        utc_timestamp(SystemTime::now()),
+    );
+
+    // O `.trashinfo` criado com create_new reserva o nome dentro da lixeira.
+    let mut counter = 1;
+    let (trash_name, info_path) = loop {
+        let candidate = if counter == 1 { name.clone() } else { format!("{}.{}", name, counter) };
+        let info_path = info_dir.join(format!("{}.trashinfo", candidate));
+        if fs::symlink_metadata(files_dir.join(&candidate)).is_err() {
+            match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+                Ok(mut file) => {
+                    file.write_all(info.as_bytes())?;
+                    break (candidate, info_path);
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {},
+                Err(e) => return Err(e),
+            }
+        }
+        counter += 1;
+    };
+
+    let destination = files_dir.join(trash_name);
+    let moved = match fs::rename(path, &destination) {
+        Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+            // A lixeira fica no disco real, fora de qualquer raiz de `ChrootFs`.
+            let metadata = vfs::DiskFs.symlink_metadata(path)?;
+            let options = CopyOptions {
+                recursive: true, conflict: Conflict::Error, preserve_permissions: true, preserve_timestamps: true,
+            };
+            copy_entry(&vfs::DiskFs, path, &destination, &metadata, &options)
+                .map_err(std::io::Error::other)
+                .and_then(|_| if metadata.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) })
+        },
+        result => result,
+    };
+    if moved.is_err() {
+        let _ = fs::remove_file(info_path);
+    }
+    moved
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Data no formato `AAAA-MM-DDThh:mm:ss` (UTC).
+fn utc_timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (days, rem) = (secs / 86400, secs % 86400);
+    // Conversão de dias desde 1970 para data civil (algoritmo de Howard Hinnant).
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}", year, month, day, rem / 3600, rem % 3600 / 60, rem % 60)
+}
+
+/// Arquivo ou diretório temporário; é apagado quando o objeto `temp` deixa de ser
+/// referenciado (ou em `io.remove_temp`/`close_all`), a menos que tenha sido criado com `keep`.
+struct TempEntry {
+    filesystem: Rc<dyn FileSystem>,
+    path: PathBuf,
+    is_dir: bool,
+    keep: bool,
+}
+
+impl TempEntry {
+    fn remove(&self) -> std::io::Result<()> {
+        let result = if self.is_dir { self.filesystem.remove_dir_all(&self.path) } else { self.filesystem.remove_file(&self.path) };
+        match result {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for TempEntry {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = self.remove();
+        }
+    }
+}
+
+impl NativeObject for TempEntry {
+    fn describe(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<temp {}>", self.path.display())
+    }
+}
+
+/// Opções de `io.temp_file`/`io.temp_dir`.
+struct TempOptions {
+    /// Onde criar; padrão: diretório temporário do sistema.
+    dir: Option<String>,
+    /// Não apaga automaticamente, útil para depuração.
+    keep: bool,
+}
+
+impl TempOptions {
+    fn parse(name: &str, options: Option<&Value>) -> Result<Self, String> {
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(TempOptions { dir: None, keep: false }),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err(format!("{} espera um dicionário de opções", name)),
+        };
+        let field = |key: &str| map.get(&Value::String(key.to_string()));
+        let keep = match field("keep") {
+            None | Some(Value::Nil) => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err(format!("{} espera um booleano na opção 'keep'", name)),
+        };
+        let dir = match field("dir") {
+            None | Some(Value::Nil) => None,
+            Some(Value::String(dir)) => Some(dir.clone()),
+            Some(_) => return Err(format!("{} espera uma string na opção 'dir'", name)),
+        };
+        Ok(TempOptions { dir, keep })
+    }
+}
+
+fn optional_string(name: &str, value: Option<&Value>) -> Result<String, String> {
+    match value {
+        None | Some(Value::Nil) => Ok(String::new()),
+        Some(Value::String(s)) => Ok(s.clone()),
+        Some(_) => Err(format!("{} espera strings como prefixo e sufixo", name)),
+    }
+}
+
+/// Cria o temporário com `FileSystem::create_temp` (sem corrida com outro processo)
+/// e retorna um objeto `temp` com `path`.
+fn create_temp(prefix: &str, suffix: &str, is_dir: bool, options: TempOptions) -> std::io::Result<Value> {
+    let filesystem = vfs::current();
+    let path = filesystem.create_temp(options.dir.as_deref().map(Path::new), prefix, suffix, is_dir)?;
+
+    // Se o caminho não for UTF-8, `entry` é descartado e o temporário apagado.
+    let entry = TempEntry { filesystem, path, is_dir, keep: options.keep };
+    let path_str = path_to_string(&entry.path).map_err(std::io::Error::other)?;
+    Ok(track("temp", vec![
+        ("path", Value::String(path_str)),
+        ("type", Value::String(if is_dir { "dir" } else { "file" }.to_string())),
+        ("keep", Value::Boolean(options.keep)),
+    ], entry))
+}
+
+/// Opções de `io.watch` (durações em milissegundos).
+struct WatchOptions {
+    /// Observa também os subdiretórios (padrão true).
+    recursive: bool,
+    /// Tempo sem novas mudanças antes de entregar os eventos, agrupados por caminho (padrão 50).
+    debounce: Duration,
+    /// Tempo sem eventos até o iterador (ou o modo callback) terminar. ATENÇÃO: o padrão
+    /// é sem limite, ou seja, sem eventos `io.watch` bloqueia a thread para sempre.
+    timeout: Option<Duration>,
+    /// "auto" (padrão; inotify no Linux), "inotify" ou "poll". Com um sistema de
+    /// arquivos instalado em `vfs`, só "poll" está disponível e "auto" o escolhe.
+    backend: String,
+    /// Intervalo de varredura do backend "poll" (padrão 500).
+    interval: Duration,
+    /// Função chamada com cada evento; retornar false encerra.
+    callback: Option<Value>,
+}
+
+impl WatchOptions {
+    fn parse(options: Option<&Value>) -> Result<Self, String> {
+        let mut parsed = WatchOptions {
+            recursive: true,
+            debounce: Duration::from_millis(50),
+            timeout: None,
+            backend: "auto".to_string(),
+            interval: Duration::from_millis(500),
+            callback: None,
+        };
+        let map = match options {
+            None | Some(Value::Nil) => return Ok(parsed),
+            Some(Value::Dict(map)) => map,
+            Some(_) => return Err("io.watch espera um dicionário de opções".to_string()),
+        };
+        let field = |key: &str| map.get(&Value::String(key.to_string())).filter(|v| **v != Value::Nil);
+        let millis = |key: &str| match field(key) {
+            None => Ok(None),
+            Some(Value::Number(ms)) if *ms >= 0.0 && ms.is_finite() => Ok(Some(Duration::from_secs_f64(ms / 1000.0))),
+            Some(_) => Err(format!("io.watch espera um número não negativo (milissegundos) na opção '{}'", key)),
+        };
+
+        match field("recursive") {
+            None => {},
+            Some(Value::Boolean(b)) => parsed.recursive = *b,
+            Some(_) => return Err("io.watch espera um booleano na opção 'recursive'".to_string()),
+        }
+        parsed.debounce = millis("debounce")?.unwrap_or(parsed.debounce);
+        parsed.timeout = millis("timeout")?;
+        parsed.interval = millis("interval")?.unwrap_or(parsed.interval).max(Duration::from_millis(1));
+        match field("backend") {
+            None => {},
+            Some(Value::String(backend)) if ["auto", "inotify", "poll"].contains(&backend.as_str()) => parsed.backend = backend.clone(),
+            Some(_) => return Err("io.watch: backend deve ser \"auto\", \"inotify\" ou \"poll\"".to_string()),
+        }
+        match field("callback") {
+            None => {},
+            Some(callback) if collections::is_callable(callback) => parsed.callback = Some(callback.clone()),
+            Some(_) => return Err("io.watch espera uma função na opção 'callback'".to_string()),
+        }
+        Ok(parsed)
+    }
+}
+
+/// Mudança bruta, antes de agrupar. `MovedFrom`/`MovedTo` com o mesmo cookie formam
+/// uma renomeação (inotify); o backend de varredura já produz `Rename`.
+enum RawKind {
+    Create,
+    Modify,
+    Delete,
+    MovedFrom(u32),
+    MovedTo(u32),
+    Rename(PathBuf),
+}
+
+struct RawEvent {
+    kind: RawKind,
+    path: PathBuf,
+}
+
+#[derive(Clone, PartialEq)]
+enum Change {
+    Create,
+    Modify,
+    Delete,
+    Rename(PathBuf),
+}
+
+/// Junta renomeações e agrupa as mudanças de um mesmo caminho: criar e depois
+/// modificar é "create", criar e apagar não gera evento, apagar e recriar é "modify".
+fn coalesce(raw: Vec<RawEvent>) -> Vec<(PathBuf, Change)> {
+    let moved_to: std::collections::HashSet<u32> = raw.iter()
+        .filter_map(|event| match event.kind { RawKind::MovedTo(cookie) => Some(cookie), _ => None })
+        .collect();
+    let mut moved_from: HashMap<u32, PathBuf> = HashMap::new();
+
+    let mut changes: Vec<Option<(PathBuf, Change)>> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    for event in raw {
+        let change = match event.kind {
+            RawKind::Create => Change::Create,
+            RawKind::Modify => Change::Modify,
+            RawKind::Delete => Change::Delete,
+            RawKind::MovedFrom(cookie) if moved_to.contains(&cookie) => {
+                moved_from.insert(cookie, event.path);
+                continue;
+            },
+            RawKind::MovedFrom(_) => Change::Delete,
+            RawKind::MovedTo(cookie) => match moved_from.remove(&cookie) {
+                Some(from) => Change::Rename(from),
+                None => Change::Create,
+            },
+            RawKind::Rename(from) => Change::Rename(from),
+        };
+
+        if let Change::Rename(_) = change {
+            index.remove(&event.path);
+            changes.push(Some((event.path, change)));
+            continue;
+        }
+        match index.get(&event.path).copied() {
+            Some(i) => {
+                let previous = changes[i].as_ref().map(|(_, change)| change.clone());
+                changes[i] = match (previous, change) {
+                    (Some(Change::Create), Change::Modify) => Some((event.path, Change::Create)),
+                    (Some(Change::Create), Change::Delete) => {
+                        index.remove(&event.path);
+                        None
+                    },
+                    (Some(Change::Delete), Change::Create) => Some((event.path, Change::Modify)),
+                    (_, change) => Some((event.path, change)),
+                };
+            },
+            None => {
+                index.insert(event.path.clone(), changes.len());
+                changes.push(Some((event.path, change)));
+            },
+        }
+    }
+    changes.into_iter().flatten().collect()
+}
+
+enum WatchBackend {
+    #[cfg(target_os = "linux")]
+    Inotify(Inotify),
+    Poll(Poller),
+}
+
+impl WatchBackend {
+    /// Espera mudanças por até `timeout` (None = sem limite); vazio se nada mudou.
+    fn wait(&mut self, timeout: Option<Duration>) -> std::io::Result<Vec<RawEvent>> {
+        match self {
+            #[cfg(target_os = "linux")]
+            WatchBackend::Inotify(inotify) => inotify.wait(timeout),
+            WatchBackend::Poll(poller) => poller.wait(timeout),
+        }
+    }
+}
+
+/// Observador de `io.watch`; o descritor do inotify é fechado ao ser descartado.
+struct Watcher {
+    backend: WatchBackend,
+    debounce: Duration,
+    timeout: Option<Duration>,
+    pending: VecDeque<Value>,
+}
+
+impl NativeObject for Watcher {
+    fn describe(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<watcher>")
+    }
+}
+
+impl Watcher {
+    fn new(roots: &[PathBuf], options: &WatchOptions) -> Result<Self, String> {
+        let filesystem = vfs::current();
+        for root in roots {
+            filesystem.symlink_metadata(root).map_err(|e| format!("Erro ao observar {}: {}", root.display(), e))?;
+        }
+        let error = |e: std::io::Error| format!("Erro ao observar: {}", e);
+        // O inotify enxerga o disco real; com outro sistema de arquivos, só a varredura.
+        if options.backend == "inotify" && !vfs::is_disk() {
+            return Err(error(std::io::Error::other("inotify só observa o disco; use o backend \"poll\"")));
+        }
+
+        #[cfg(target_os = "linux")]
+        let backend = match options.backend.as_str() {
+            "poll" => WatchBackend::Poll(Poller::new(filesystem, roots, options)),
+            _ if !vfs::is_disk() => WatchBackend::Poll(Poller::new(filesystem, roots, options)),
+            "inotify" => WatchBackend::Inotify(Inotify::new(roots, options.recursive).map_err(error)?),
+            // Sem inotify disponível (ex.: limite de watches), cai para a varredura.
+            _ => match Inotify::new(roots, options.recursive) {
+                Ok(inotify) => WatchBackend::Inotify(inotify),
+                Err(_) => WatchBackend::Poll(Poller::new(filesystem, roots, options)),
+            },
+        };
+        #[cfg(not(target_os = "linux"))]
+        let backend = match options.backend.as_str() {
+            "inotify" => return Err(error(std::io::Error::other("inotify só está disponível no Linux"))),
+            _ => WatchBackend::Poll(Poller::new(filesystem, roots, options)),
+        };
+
+        Ok(Watcher { backend, debounce: options.debounce, timeout: options.timeout, pending: VecDeque::new() })
+    }
+
+    /// Próximo evento, esperando se necessário; None quando o `timeout` expira.
+    fn next_event(&mut self) -> Result<Option<Value>, String> {
+        let error = |e: std::io::Error| format!("Erro ao observar: {}", e);
+        while self.pending.is_empty() {
+            let mut raw = self.backend.wait(self.timeout).map_err(error)?;
+            if raw.is_empty() {
+                return Ok(None);
+            }
+            // Debounce: continua juntando até ficar `debounce` sem mudanças.
+            loop {
+                let more = self.backend.wait(Some(self.debounce)).map_err(error)?;
+                if more.is_empty() {
+                    break;
+                }
+                raw.extend(more);
+            }
+            for (path, change) in coalesce(raw) {
+                let (kind, from) = match change {
+                    Change::Create => ("create", None),
+                    Change::Modify => ("modify", None),
+                    Change::Delete => ("delete", None),
+                    Change::Rename(from) => ("rename", Some(from)),
+                };
+                let mut fields = vec![
+                    ("type", Value::String(kind.to_string())),
+                    ("path", Value::String(path_to_string(&path)?)),
+                ];
+                if let Some(from) = from {
+                    fields.push(("from", Value::String(path_to_string(&from)?)));
+                }
+                self.pending.push_back(record(fields));
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+#[derive(PartialEq)]
+struct EntryState {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+    inode: Option<(u64, u64)>,
+}
+
+/// Backend portátil: compara varreduras sucessivas dos caminhos observados.
+/// Renomeações são reconhecidas pelo inode (e metadados) quando a plataforma o fornece.
+struct Poller {
+    filesystem: Rc<dyn FileSystem>,
+    roots: Vec<PathBuf>,
+    recursive: bool,
+    interval: Duration,
+    snapshot: HashMap<PathBuf, EntryState>,
+}
+
+impl Poller {
+    fn new(filesystem: Rc<dyn FileSystem>, roots: &[PathBuf], options: &WatchOptions) -> Self {
+        let mut poller = Poller {
+            filesystem,
+            roots: roots.to_vec(),
+            recursive: options.recursive,
+            interval: options.interval,
+            snapshot: HashMap::new(),
+        };
+        poller.snapshot = poller.scan();
+        poller
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, EntryState> {
+        fn visit(filesystem: &dyn FileSystem, path: &Path, depth: usize, recursive: bool, out: &mut HashMap<PathBuf, EntryState>) {
+            let Ok(info) = filesystem.symlink_metadata(path) else { return };
+            out.insert(path.to_path_buf(), EntryState {
+                is_dir: info.is_dir(),
+                len: info.len,
+                modified: info.modified,
+                inode: info.id,
+            });
+            if info.is_dir() && (depth == 0 || recursive) {
+                for name in filesystem.read_dir(path).into_iter().flatten() {
+                    visit(filesystem, &path.join(name), depth + 1, recursive, out);
+                }
+            }
+        }
+
+        let mut entries = HashMap::new();
+        for root in &self.roots {
+            visit(self.filesystem.as_ref(), root, 0, self.recursive, &mut entries);
+        }
+        entries
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> std::io::Result<Vec<RawEvent>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let pause = match deadline {
+                Some(deadline) => self.interval.min(deadline.saturating_duration_since(Instant::now())),
+                None => self.interval,
+            };
+            std::thread::sleep(pause);
+
+            let current = self.scan();
+            let previous = std::mem::replace(&mut self.snapshot, current);
+            let events = self.diff(&previous);
+            if !events.is_empty() || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Ok(events);
+            }
+        }
+    }
+
+    fn diff(&self, previous: &HashMap<PathBuf, EntryState>) -> Vec<RawEvent> {
+        let mut deleted: Vec<&PathBuf> = previous.keys().filter(|path| !self.snapshot.contains_key(*path)).collect();
+        let mut created: Vec<&PathBuf> = self.snapshot.keys().filter(|path| !previous.contains_key(*path)).collect();
+        deleted.sort();
+        created.sort();
+
+        let mut events = Vec::new();
+        for path in created {
+            // Inodes são reutilizados: exige também mesmo tipo, tamanho e data.
+            let state = &self.snapshot[path];
+            let renamed = deleted.iter().position(|old| state.inode.is_some() && previous[*old] == *state);
+            match renamed {
+                Some(i) => {
+                    let from = deleted.remove(i);
+                    events.push(RawEvent { kind: RawKind::Rename(from.clone()), path: path.clone() });
+                },
+                None => events.push(RawEvent { kind: RawKind::Create, path: path.clone() }),
+            }
+        }
+        for path in deleted {
+            events.push(RawEvent { kind: RawKind::Delete, path: path.clone() });
+        }
+
+        let mut modified: Vec<&PathBuf> = self.snapshot.iter()
+            .filter(|(path, state)| {
+                // Diretórios mudam de data quando o conteúdo muda; isso já vira create/delete.
+                previous.get(*path).is_some_and(|old| !state.is_dir && old != *state)
+            })
+            .map(|(path, _)| path)
+            .collect();
+        modified.sort();
+        for path in modified {
+            events.push(RawEvent { kind: RawKind::Modify, path: path.clone() });
+        }
+        events
+    }
+}
+
+/// Backend do Linux: um watch do inotify por diretório observado.
+#[cfg(target_os = "linux")]
+struct Inotify {
+    file: File,
+    watches: HashMap<i32, PathBuf>,
+    roots: Vec<PathBuf>,
+    recursive: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Inotify {
+    const MASK: u32 = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_MOVED_FROM | libc::IN_MOVED_TO
+        | libc::IN_CREATE | libc::IN_DELETE | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+    fn new(roots: &[PathBuf], recursive: bool) -> std::io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        // SAFETY: inotify_init1 só recebe flags e não acessa memória do processo.
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: `fd` é um descritor válido recém-criado e sem outro dono; o File
+        // passa a ser dono dele e o fecha ao ser descartado.
+        let file = unsafe { File::from_raw_fd(fd) };
+        let mut inotify = Inotify { file, watches: HashMap::new(), roots: roots.to_vec(), recursive };
+        for root in roots {
+            if root.is_dir() {
+                inotify.add_tree(root, true, None)?;
+            } else {
+                inotify.add_watch(root)?;
+            }
+        }
+        Ok(inotify)
+    }
+
+    fn add_watch(&mut self, path: &Path) -> std::io::Result<()> {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::ffi::OsStrExt;
+
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "caminho contém byte nulo"))?;
+        // SAFETY: o descritor pertence a `self.file` e `c_path` é uma string C válida
+        // que vive até o fim da chamada.
+        let wd = unsafe { libc::inotify_add_watch(self.file.as_raw_fd(), c_path.as_ptr(), Self::MASK) };
+        if wd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        self.watches.insert(wd, path.to_path_buf());
+        Ok(())
+    }
+
+    /// Observa `dir` (e subdiretórios, se recursivo). `created` recebe o que já existia
+    /// num diretório novo, criado antes de o watch ser adicionado.
+    fn add_tree(&mut self, dir: &Path, is_root: bool, mut created: Option<&mut Vec<RawEvent>>) -> std::io::Result<()> {
+        self.add_watch(dir)?;
+        if !is_root && !self.recursive {
+            return Ok(());
+        }
+        let mut children: Vec<_> = fs::read_dir(dir)?.flatten().map(|entry| entry.path()).collect();
+        children.sort();
+        for child in children {
+            if let Some(created) = created.as_deref_mut() {
+                created.push(RawEvent { kind: RawKind::Create, path: child.clone() });
+            }
+            let is_dir = fs::symlink_metadata(&child).is_ok_and(|m| m.is_dir());
+            if is_dir && self.recursive {
+                // O diretório pode sumir entre a listagem e o watch.
+                let _ = self.add_tree(&child, false, created.as_deref_mut());
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_tree(&mut self, dir: &Path) {
+        use std::os::fd::AsRawFd;
+
+        let stale: Vec<i32> = self.watches.iter()
+            .filter(|(_, path)| path.starts_with(dir))
+            .map(|(wd, _)| *wd)
+            .collect();
+        for wd in stale {
+            self.watches.remove(&wd);
+            // SAFETY: o descritor pertence a `self.file`; um `wd` já inválido só gera EINVAL.
+            unsafe { libc::inotify_rm_watch(self.file.as_raw_fd(), wd) };
+        }
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> std::io::Result<Vec<RawEvent>> {
+        use std::os::fd::AsRawFd;
+
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let millis = match deadline {
+                Some(deadline) => deadline.saturating_duration_since(Instant::now()).as_millis().min(i32::MAX as u128) as i32,
+                None => -1,
+            };
+            let mut poll_fd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            // SAFETY: `poll_fd` é um único pollfd válido e exclusivo durante a chamada.
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, millis) };
+            if ready < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(error);
+            }
+            if ready == 0 {
+                return Ok(Vec::new());
+            }
+
+            let read = match self.file.read(&mut buffer) {
+                Ok(read) => read,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            };
+            let events = self.parse(&buffer[..read]);
+            if !events.is_empty() {
+                return Ok(events);
+            }
+        }
+    }
+
+    /// Decodifica uma sequência de `struct inotify_event`, cada um seguido de
+    /// `len` bytes com o nome (terminado em zero).
+    fn parse(&mut self, buffer: &[u8]) -> Vec<RawEvent> {
+        use libc::{IN_ATTRIB, IN_CREATE, IN_DELETE, IN_DELETE_SELF, IN_IGNORED, IN_ISDIR, IN_MODIFY,
+            IN_MOVED_FROM, IN_MOVED_TO, IN_MOVE_SELF, IN_Q_OVERFLOW};
+        use std::os::unix::ffi::OsStrExt;
+
+        const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+        let mut events = Vec::new();
+        let mut offset = 0;
+        while offset + HEADER <= buffer.len() {
+            // SAFETY: o laço garante `HEADER` bytes a partir de `offset`, e todo padrão de
+            // bits é um inotify_event válido. O buffer não tem alinhamento garantido,
+            // por isso read_unaligned.
+            let event = unsafe { std::ptr::read_unaligned(buffer[offset..].as_ptr().cast::<libc::inotify_event>()) };
+            let (wd, mask, cookie) = (event.wd, event.mask, event.cookie);
+            let name_start = offset + HEADER;
+            let name = &buffer[name_start..(name_start + event.len as usize).min(buffer.len())];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+            offset = name_start + event.len as usize;
+
+            if mask & IN_Q_OVERFLOW != 0 {
+                // Eventos foram perdidos: avisa que tudo pode ter mudado.
+                events.extend(self.roots.iter().map(|root| RawEvent { kind: RawKind::Modify, path: root.clone() }));
+                continue;
+            }
+            let Some(dir) = self.watches.get(&wd).cloned() else { continue };
+            if mask & IN_IGNORED != 0 {
+                self.watches.remove(&wd);
+                continue;
+            }
+            let path = if name.is_empty() { dir.clone() } else { dir.join(std::ffi::OsStr::from_bytes(name)) };
+            let is_dir = mask & IN_ISDIR != 0;
+
+            if mask & IN_CREATE != 0 {
+                events.push(RawEvent { kind: RawKind::Create, path: path.clone() });
+            }
+            if mask & IN_MOVED_TO != 0 {
+                events.push(RawEvent { kind: RawKind::MovedTo(cookie), path: path.clone() });
+            }
+            if mask & (IN_CREATE | IN_MOVED_TO) != 0 && is_dir && self.recursive {
+                let _ = self.add_tree(&path, false, Some(&mut events));
+            }
+            if mask & IN_MOVED_FROM != 0 {
+                if is_dir {
+                    self.remove_tree(&path);
+                }
+                events.push(RawEvent { kind: RawKind::MovedFrom(cookie), path: path.clone() });
+            }
+            if mask & IN_DELETE != 0 {
+                events.push(RawEvent { kind: RawKind::Delete, path: path.clone() });
+            }
+            if mask & (IN_MODIFY | IN_ATTRIB) != 0 && !is_dir {
+                events.push(RawEvent { kind: RawKind::Modify, path: path.clone() });
+            }
+            // Subdiretórios apagados já aparecem como IN_DELETE no diretório pai.
+            if mask & (IN_DELETE_SELF | IN_MOVE_SELF) != 0 && self.roots.contains(&dir) {
+                events.push(RawEvent { kind: RawKind::Delete, path });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, scratch, text};
+
+    fn call(name: &str, args: Vec<Value>) -> Result<Value, String> {
+        test_support::call(&create_module(), name, args)
+    }
+
+    /// Instala um `MemoryFs` vazio nesta thread (cada teste roda na sua).
+    fn memory() -> Rc<vfs::MemoryFs> {
+        let memory = Rc::new(vfs::MemoryFs::new());
+        vfs::set_filesystem(memory.clone());
+        memory
+    }
+
+    fn overwrite() -> Value {
+        let mut options = indexmap::IndexMap::new();
+        options.insert(text("on_conflict"), text("overwrite"));
+        options.insert(text("recursive"), Value::Boolean(true));
+        Value::Dict(options)
+    }
+
+    fn field(record: &Value, key: &str) -> Value {
+        match record {
+            Value::Dict(map) => map.get(&text(key)).cloned().unwrap_or(Value::Nil),
+            _ => panic!("registro esperado"),
+        }
+    }
+
+    #[test]
+    fn copy_and_move_onto_the_same_file_fail_without_deleting_it() {
+        let dir = scratch("same-file");
+        fs::create_dir(&dir).unwrap();
+        fs::write(format!("{}/a.txt", dir), "conteúdo").unwrap();
+        fs::hard_link(format!("{}/a.txt", dir), format!("{}/b.txt", dir)).unwrap();
+        vfs::set_filesystem(Rc::new(vfs::ChrootFs::new(&dir).unwrap()));
+
+        for dst in ["/a.txt", "/./a.txt", "/b.txt"] {
+            assert!(call("copy", vec![text("/a.txt"), text(dst), overwrite()]).is_err());
+            assert!(call("move", vec![text("/a.txt"), text(dst), overwrite()]).is_err());
+        }
+        assert!(call("copy", vec![text("/"), text("/."), overwrite()]).is_err());
+        assert_eq!(call("read_file", vec![text("/a.txt")]).unwrap(), text("conteúdo"));
+
+        vfs::clear_filesystem();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn dropped_lock_is_released() {
+        let memory = memory();
+        let busy = |memory: &vfs::MemoryFs| memory.lock(Path::new("/lock"), false, false).is_err();
+        let lock = call("lock", vec![text("/lock")]).unwrap();
+        assert!(busy(&memory));
+        assert_eq!(call("try_lock", vec![text("/lock")]).unwrap(), Value::Nil);
+        drop(lock);
+        assert!(!busy(&memory));
+
+        let lock = call("try_lock", vec![text("/lock")]).unwrap();
+        assert_eq!(call("unlock", vec![lock.clone()]).unwrap(), Value::Boolean(true));
+        assert!(!busy(&memory));
+        assert_eq!(call("unlock", vec![lock]).unwrap(), Value::Boolean(false));
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn dropped_temp_is_deleted_unless_kept() {
+        let memory = memory();
+        memory.add_dir("/tmp");
+        let exists = |temp: &Value| match collections::object_field(temp, "path") {
+            Some(Value::String(path)) => memory.metadata(Path::new(path)).is_ok(),
+            _ => panic!("temporário sem path"),
+        };
+        let temp = call("temp_file", vec![text("a-"), text(".txt")]).unwrap();
+        let copy = temp.clone();
+        drop(temp);
+        assert!(exists(&copy));
+        let path = collections::object_field(&copy, "path").unwrap().to_string();
+        assert!(path.starts_with("/tmp/a-") && path.ends_with(".txt"));
+        drop(copy);
+        assert!(memory.read_dir(Path::new("/tmp")).unwrap().is_empty());
+
+        let mut keep = indexmap::IndexMap::new();
+        keep.insert(text("keep"), Value::Boolean(true));
+        let dir = call("temp_dir", vec![Value::Nil, Value::Dict(keep)]).unwrap();
+        let names = memory.read_dir(Path::new("/tmp")).unwrap();
+        drop(dir);
+        assert_eq!(memory.read_dir(Path::new("/tmp")).unwrap(), names);
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn watch_reports_changes_until_unwatch() {
+        let dir = scratch("watch");
+        fs::create_dir(&dir).unwrap();
+        let mut options = indexmap::IndexMap::new();
+        options.insert(text("timeout"), Value::Number(2000.0));
+        options.insert(text("debounce"), Value::Number(20.0));
+        let events = call("watch", vec![text(&dir), Value::Dict(options)]).unwrap();
+
+        fs::write(format!("{}/novo.txt", dir), "x").unwrap();
+        let event = collections::open_iter(&events).unwrap().next().unwrap().unwrap();
+        assert!(collections::object_field(&event, "path").unwrap().to_string().ends_with("novo.txt"));
+
+        assert_eq!(call("unwatch", vec![events.clone()]).unwrap(), Value::Boolean(true));
+        assert_eq!(call("unwatch", vec![events.clone()]).unwrap(), Value::Boolean(false));
+        assert!(collections::open_iter(&events).unwrap().next().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn append_file_encodes_without_repeating_the_bom() {
+        let memory = memory();
+        for _ in 0..2 {
+            call("append_file", vec![text("/a.txt"), text("é"), text("utf-16le")]).unwrap();
+        }
+        assert_eq!(memory.read(Path::new("/a.txt")).unwrap(), [0xe9, 0x00, 0xe9, 0x00]);
+
+        let mut options = indexmap::IndexMap::new();
+        options.insert(text("encoding"), text("utf-16"));
+        for _ in 0..2 {
+            call("append_file", vec![text("/b.txt"), text("a"), Value::Dict(options.clone())]).unwrap();
+        }
+        assert_eq!(memory.read(Path::new("/b.txt")).unwrap(), [0xff, 0xfe, b'a', 0x00, b'a', 0x00]);
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn dropped_file_is_flushed_and_closed() {
+        let memory = memory();
+        let file = call("open", vec![text("/dropped.txt"), text("w")]).unwrap();
+        call("write", vec![file.clone(), text("sem close")]).unwrap();
+        assert_eq!(memory.read(Path::new("/dropped.txt")).unwrap(), b"");
+        drop(file);
+        assert_eq!(memory.read(Path::new("/dropped.txt")).unwrap(), b"sem close");
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn seek_rejects_negative_offset_from_start_and_tell_ignores_buffering() {
+        memory().add_file("/seek.txt", "0123456789");
+        let file = call("open", vec![text("/seek.txt")]).unwrap();
+        assert!(call("seek", vec![file.clone(), Value::Number(-1.0)]).is_err());
+        assert!(call("seek", vec![file.clone(), Value::Number(-1.0), text("start")]).is_err());
+
+        assert_eq!(call("read", vec![file.clone(), Value::Number(3.0)]).unwrap(), text("012"));
+        assert_eq!(call("tell", vec![file.clone()]).unwrap(), Value::Number(3.0));
+        assert_eq!(call("seek", vec![file.clone(), Value::Number(-2.0), text("end")]).unwrap(), Value::Number(8.0));
+        assert_eq!(call("close", vec![file.clone()]).unwrap(), Value::Boolean(true));
+        assert_eq!(call("close", vec![file.clone()]).unwrap(), Value::Boolean(false));
+        assert!(call("tell", vec![file]).is_err());
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn binary_mode_reads_bytes() {
+        memory().add_file("/binary.bin", [0xffu8, b'\n', 1]);
+        let file = call("open", vec![text("/binary.bin"), text("rb")]).unwrap();
+        assert_eq!(call("read_line", vec![file.clone()]).unwrap(), test_support::numbers([255.0]));
+        assert_eq!(call("read", vec![file.clone()]).unwrap(), test_support::numbers([1.0]));
+        assert_eq!(call("read_line", vec![file]).unwrap(), Value::Nil);
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn write_atomic_keeps_the_old_content_when_the_disk_is_full() {
+        let memory = memory();
+        memory.add_file("/dados.txt", "antigo");
+        memory.set_capacity(Some(10));
+        assert!(call("write_atomic", vec![text("/dados.txt"), text("conteúdo novo")]).is_err());
+        assert_eq!(memory.read(Path::new("/dados.txt")).unwrap(), b"antigo");
+        assert_eq!(memory.read_dir(Path::new("/")).unwrap(), ["dados.txt"]);
+
+        assert_eq!(call("write_atomic", vec![text("/dados.txt"), text("novo")]), Ok(Value::Boolean(true)));
+        assert_eq!(memory.read(Path::new("/dados.txt")).unwrap(), b"novo");
+        assert_eq!(memory.used_bytes(), 4);
+        vfs::clear_filesystem();
+    }
+
+    #[test]
+    fn raw_lines_drop_crlf_and_stream_arguments_are_checked() {
+        let mut input = std::io::Cursor::new(b"um\r\ndois\n\ntres".to_vec());
+        assert_eq!(read_raw_line(&mut input).unwrap(), Some(b"um".to_vec()));
+        assert_eq!(read_raw_line(&mut input).unwrap(), Some(b"dois".to_vec()));
+        assert_eq!(read_raw_line(&mut input).unwrap(), Some(Vec::new()));
+        assert_eq!(read_raw_line(&mut input).unwrap(), Some(b"tres".to_vec()));
+        assert_eq!(read_raw_line(&mut input).unwrap(), None);
+
+        assert!(matches!(call("is_tty", vec![text("stdin")]), Ok(Value::Boolean(_))));
+        assert!(call("is_tty", vec![text("stdio")]).is_err());
+        assert!(call("flush_stdout", vec![text("stdout")]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chroot_walk_and_symlinks_stay_inside_the_root() {
+        let dir = scratch("chroot");
+        let root = format!("{}/root", dir);
+        fs::create_dir_all(format!("{}/sub", root)).unwrap();
+        fs::write(format!("{}/secret.txt", dir), "fora").unwrap();
+        std::os::unix::fs::symlink("../..", format!("{}/sub/out", root)).unwrap();
+        vfs::set_filesystem(Rc::new(vfs::ChrootFs::new(&root).unwrap()));
+
+        let mut options = indexmap::IndexMap::new();
+        options.insert(text("follow_symlinks"), Value::Boolean(true));
+        let entries = match call("walk", vec![text("/"), Value::Dict(options)]).unwrap() {
+            Value::List(entries) => entries,
+            _ => unreachable!(),
+        };
+        let paths: Vec<Value> = entries.iter().map(|entry| field(entry, "path")).collect();
+        assert_eq!(paths, vec![text("/sub"), text("/sub/out")]);
+        assert_eq!(field(&entries[1], "type"), text("symlink"));
+
+        assert!(call("symlink", vec![text("../../secret.txt"), text("/sub/leak")]).is_err());
+        assert!(call("symlink", vec![text("../../../secret.txt"), text("/leak")]).is_err());
+        assert!(call("symlink", vec![text("../sub"), text("/sub/up")]).is_ok());
+        assert!(call("read_link", vec![text("/sub/out")]).is_ok());
+        assert!(call("canonicalize", vec![text("/sub/out")]).is_err());
+
+        vfs::clear_filesystem();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn delete_removes_the_link_not_its_target() {
+        let dir = scratch("links");
+        fs::create_dir_all(format!("{}/alvo/sub", dir)).unwrap();
+        vfs::set_filesystem(Rc::new(vfs::ChrootFs::new(&dir).unwrap()));
+
+        assert_eq!(call("symlink", vec![text("alvo"), text("/link")]), Ok(Value::Boolean(true)));
+        assert_eq!(call("is_symlink", vec![text("/link")]), Ok(Value::Boolean(true)));
+        assert_eq!(call("read_link", vec![text("/link")]), Ok(text("alvo")));
+        assert_eq!(call("canonicalize", vec![text("/link/sub/..")]), Ok(text("/alvo")));
+        assert_eq!(call("delete", vec![text("/link")]), Ok(Value::Boolean(true)));
+        assert_eq!(call("is_symlink", vec![text("/link")]), Ok(Value::Boolean(false)));
+        assert_eq!(call("is_dir", vec![text("/alvo/sub")]), Ok(Value::Boolean(true)));
+
+        vfs::clear_filesystem();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn copy_stat_and_touch_go_through_the_current_filesystem() {
+        let memory = memory();
+        memory.add_file("/src/a.txt", "a");
+        memory.add_file("/src/sub/b.txt", "b");
+
+        let mut options = indexmap::IndexMap::new();
+        options.insert(text("recursive"), Value::Boolean(true));
+        options.insert(text("preserve"), Value::Boolean(true));
+        assert_eq!(call("copy", vec![text("/src"), text("/dst"), Value::Dict(options)]), Ok(Value::Number(2.0)));
+        assert_eq!(memory.read(Path::new("/dst/sub/b.txt")).unwrap(), b"b");
+
+        assert_eq!(call("touch", vec![text("/novo.txt"), Value::Number(1000.0)]), Ok(Value::Boolean(true)));
+        assert_eq!(call("set_mtime", vec![text("/dst/a.txt"), Value::Number(2000.0)]), Ok(Value::Boolean(true)));
+        let touched = call("stat", vec![text("/novo.txt")]).unwrap();
+        assert_eq!(field(&touched, "type"), text("file"));
+        assert_eq!(field(&touched, "mtime"), Value::Number(1000.0));
+        assert_eq!(field(&touched, "inode"), Value::Nil);
+        let copied = call("lstat", vec![text("/dst/a.txt")]).unwrap();
+        assert_eq!(field(&copied, "mtime"), Value::Number(2000.0));
+        assert_eq!(field(&copied, "is_symlink"), Value::Boolean(false));
+
+        vfs::clear_filesystem();
+    }
+}
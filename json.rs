@@ -1,5 +1,6 @@
 use crate::value::Value;
 use crate::symbol_table::SymbolTable;
+use indexmap::IndexMap;
 use std::collections::HashMap;
 
 /// Registra funções de JSON na stdlib
@@ -39,7 +40,8 @@ pub fn register(globals: &mut SymbolTable) {
     });
 }
 
-/// Converte serde_json::Value para nosso Value
+/// Converte serde_json::Value para nosso Value. Com o recurso `preserve_order`
+/// do serde_json, as chaves dos objetos chegam na ordem do texto.
 fn json_to_value(json: &serde_json::Value) -> Value {
     match json {
         serde_json::Value::Null => Value::Nil,
@@ -57,7 +59,7 @@ fn json_to_value(json: &serde_json::Value) -> Value {
             Value::List(values)
         },
         serde_json::Value::Object(obj) => {
-            let mut map = HashMap::new();
+            let mut map = IndexMap::new();
             for (key, value) in obj {
                 map.insert(Value::String(key.clone()), json_to_value(value));
             }
@@ -136,3 +138,22 @@ pub fn create_module() -> Value {
     let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
     Value::Dict(dict_map)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_stringify_keep_key_order() {
+        let text = r#"{"zeta":true,"alpha":{"y":null,"x":"b"},"mid":false}"#;
+        let value = json_to_value(&serde_json::from_str(text).unwrap());
+        assert_eq!(serde_json::to_string(&value_to_json(&value)).unwrap(), text);
+    }
+
+    #[test]
+    fn dicts_with_different_key_order_are_equal() {
+        let a = json_to_value(&serde_json::from_str(r#"{"a":true,"b":null}"#).unwrap());
+        let b = json_to_value(&serde_json::from_str(r#"{"b":null,"a":true}"#).unwrap());
+        assert_eq!(a, b);
+    }
+}
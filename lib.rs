@@ -0,0 +1,27 @@
+pub mod value;
+pub mod symbol_table;
+
+pub mod collections;
+pub mod dict;
+pub mod encoding;
+pub mod glob;
+pub mod http;
+pub mod io;
+pub mod json;
+pub mod math;
+pub mod path;
+pub mod string;
+pub mod sys;
+pub mod vfs;
+
+mod util;
+
+/// Registra como o interpretador executa funções Snask chamadas por funções nativas
+/// e o que `sys.exit` deve fechar antes de encerrar o processo.
+pub fn init(caller: collections::FunctionCaller) {
+    collections::set_function_caller(caller);
+    sys::at_exit(io::close_all);
+}
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,190 @@
+use crate::value::Value;
+use super::util::{home_dir, path_to_string};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Cria e retorna o objeto do módulo `path` com todas as suas funções.
+/// As operações são léxicas (não consultam o disco), exceto `absolute` e `expand_home`.
+pub fn create_module() -> Value {
+    let mut module = HashMap::new();
+
+    module.insert("join".to_string(), Value::NativeFunction(|args| {
+        if args.is_empty() { return Err("path.join espera pelo menos 1 argumento".to_string()); }
+
+        // Como em std::path, uma parte absoluta substitui tudo o que veio antes.
+        let mut joined = PathBuf::new();
+        for arg in &args {
+            joined.push(string_arg("path.join", arg)?);
+        }
+        path_value(&joined)
+    }));
+
+    module.insert("dirname".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.dirname", &args)?;
+        match path.parent() {
+            Some(parent) => path_value(parent),
+            // Só a raiz (ou um prefixo no Windows) não tem pai.
+            None if path.has_root() => path_value(path),
+            None => Ok(Value::String(String::new())),
+        }
+    }));
+
+    module.insert("basename".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.basename", &args)?;
+        os_value(path.file_name())
+    }));
+
+    module.insert("stem".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.stem", &args)?;
+        os_value(path.file_stem())
+    }));
+
+    module.insert("extension".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.extension", &args)?;
+        os_value(path.extension())
+    }));
+
+    module.insert("with_extension".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("path.with_extension espera 2 argumentos".to_string()); }
+
+        let path = Path::new(string_arg("path.with_extension", &args[0])?);
+        let extension = string_arg("path.with_extension", &args[1])?;
+        if path.file_name().is_none() {
+            return Err(format!("path.with_extension: '{}' não tem nome de arquivo", path.display()));
+        }
+        path_value(&path.with_extension(extension.strip_prefix('.').unwrap_or(extension)))
+    }));
+
+    module.insert("normalize".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.normalize", &args)?;
+        path_value(&normalize(path))
+    }));
+
+    module.insert("absolute".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.absolute", &args)?;
+        let absolute = std::path::absolute(path).map_err(|e| format!("path.absolute: {}", e))?;
+        path_value(&normalize(&absolute))
+    }));
+
+    module.insert("relative_to".to_string(), Value::NativeFunction(|args| {
+        if args.len() != 2 { return Err("path.relative_to espera 2 argumentos".to_string()); }
+
+        let path = Path::new(string_arg("path.relative_to", &args[0])?);
+        let base = Path::new(string_arg("path.relative_to", &args[1])?);
+        if path.is_absolute() != base.is_absolute() {
+            return Err("path.relative_to espera dois caminhos absolutos ou dois relativos".to_string());
+        }
+        path_value(&relative_to(&normalize(path), &normalize(base))?)
+    }));
+
+    module.insert("is_absolute".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.is_absolute", &args)?;
+        Ok(Value::Boolean(path.is_absolute()))
+    }));
+
+    module.insert("components".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.components", &args)?;
+        let parts = path.components().map(|component| {
+            os_value(Some(component.as_os_str()))
+        }).collect::<Result<Vec<_>, _>>()?;
+        Ok(Value::List(parts))
+    }));
+
+    module.insert("expand_home".to_string(), Value::NativeFunction(|args| {
+        let path = single_arg("path.expand_home", &args)?;
+        let text = path.to_str().unwrap_or_default();
+        let rest = match text.strip_prefix('~') {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => rest.trim_start_matches('/'),
+            Some(_) => return Err(format!("path.expand_home: '~usuário' não é suportado em '{}'", text)),
+            None => return Ok(Value::String(text.to_string())),
+        };
+        let home = home_dir().ok_or("path.expand_home: variável HOME não definida")?;
+        path_value(&if rest.is_empty() { home } else { home.join(rest) })
+    }));
+
+    let dict_map = module.into_iter().map(|(k, v)| (Value::String(k), v)).collect();
+    Value::Dict(dict_map)
+}
+
+fn string_arg<'a>(name: &str, value: &'a Value) -> Result<&'a str, String> {
+    match value {
+        Value::String(s) => Ok(s),
+        _ => Err(format!("{} espera uma string (caminho)", name)),
+    }
+}
+
+fn single_arg<'a>(name: &str, args: &'a [Value]) -> Result<&'a Path, String> {
+    if args.len() != 1 { return Err(format!("{} espera 1 argumento", name)); }
+    string_arg(name, &args[0]).map(Path::new)
+}
+
+fn path_value(path: &Path) -> Result<Value, String> {
+    path_to_string(path).map(Value::String)
+}
+
+/// Parte ausente (ex.: arquivo sem extensão) vira string vazia.
+fn os_value(part: Option<&std::ffi::OsStr>) -> Result<Value, String> {
+    match part {
+        Some(part) => path_value(Path::new(part)),
+        None => Ok(Value::String(String::new())),
+    }
+}
+
+/// Remove `.` e resolve `..` sem consultar o disco. `..` no início de um caminho
+/// relativo é mantido; acima da raiz é descartado. Caminho vazio vira ".".
+fn normalize(path: &Path) -> PathBuf {
+    let mut parts: Vec<Component> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => match parts.last() {
+                Some(Component::Normal(_)) => { parts.pop(); },
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                _ => parts.push(component),
+            },
+            _ => parts.push(component),
+        }
+    }
+    if parts.is_empty() {
+        return PathBuf::from(".");
+    }
+    parts.iter().collect()
+}
+
+/// Caminho de `base` até `path`, ambos já normalizados.
+fn relative_to(path: &Path, base: &Path) -> Result<PathBuf, String> {
+    let path_parts: Vec<_> = path.components().filter(|c| *c != Component::CurDir).collect();
+    let base_parts: Vec<_> = base.components().filter(|c| *c != Component::CurDir).collect();
+    let common = path_parts.iter().zip(&base_parts).take_while(|(a, b)| a == b).count();
+
+    if base_parts[common..].contains(&Component::ParentDir) {
+        return Err(format!("path.relative_to: não é possível sair de '{}' com '..'", base.display()));
+    }
+    if common == 0 && path.has_root() {
+        // Prefixos diferentes no Windows (ex.: C: e D:).
+        return Err(format!("path.relative_to: '{}' e '{}' não têm raiz comum", path.display(), base.display()));
+    }
+
+    let mut relative: PathBuf = base_parts[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path_parts[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_and_relative_to_are_lexical() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), PathBuf::from("a/c"));
+        assert_eq!(normalize(Path::new("../a/..")), PathBuf::from(".."));
+        assert_eq!(normalize(Path::new("/../a")), PathBuf::from("/a"));
+        assert_eq!(normalize(Path::new("a/..")), PathBuf::from("."));
+        assert_eq!(relative_to(Path::new("/a/b/c"), Path::new("/a/d")).unwrap(), PathBuf::from("../b/c"));
+        assert_eq!(relative_to(Path::new("/a"), Path::new("/a")).unwrap(), PathBuf::from("."));
+        assert!(relative_to(Path::new("a"), Path::new("../b")).is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use crate::value::Value;
+use std::collections::HashMap;
+
+/// Variável registrada na tabela de símbolos.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub value: Value,
+    pub mutable: bool,
+    pub exported: bool,
+}
+
+/// Escopo global onde os módulos nativos registram funções e constantes.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Define (ou redefine) um símbolo.
+    pub fn define(&mut self, name: String, value: Value, mutable: bool, exported: bool) {
+        self.symbols.insert(name, Symbol { value, mutable, exported });
+    }
+
+    /// Atalho para registrar uma função nativa imutável.
+    pub fn define_native_function(&mut self, name: &str, function: fn(Vec<Value>) -> Result<Value, String>) {
+        self.define(name.to_string(), Value::NativeFunction(function), false, false);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.symbols.get(name).map(|symbol| &symbol.value)
+    }
+}
@@ -1,7 +1,29 @@
 use crate::value::Value;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+thread_local! {
+    static EXIT_HOOKS: RefCell<Vec<fn()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registra `hook` para ser executado por `sys.exit` antes de encerrar o processo.
+pub fn at_exit(hook: fn()) {
+    EXIT_HOOKS.with(|hooks| {
+        let mut hooks = hooks.borrow_mut();
+        if !hooks.iter().any(|registered| std::ptr::fn_addr_eq(*registered, hook)) {
+            hooks.push(hook);
+        }
+    });
+}
+
+/// Executa os ganchos de `at_exit`, na ordem em que foram registrados.
+fn run_exit_hooks() {
+    for hook in EXIT_HOOKS.with(|hooks| std::mem::take(&mut *hooks.borrow_mut())) {
+        hook();
+    }
+}
+
 /// Cria e retorna o objeto do módulo `sys` com todas as suas funções.
 pub fn create_module() -> Value {
     let mut module = HashMap::new();
@@ -36,13 +58,15 @@ pub fn create_module() -> Value {
             }
         };
         
+        // process::exit não executa destrutores: os módulos liberam seus recursos antes.
+        run_exit_hooks();
         std::process::exit(code);
     }));
 
     module.insert("args".to_string(), Value::NativeFunction(|_args| {
         let args: Vec<Value> = std::env::args()
             .skip(1) // Pula o nome do executável
-            .map(|arg| Value::String(arg))
+            .map(Value::String)
             .collect();
         Ok(Value::List(args))
     }));
@@ -0,0 +1,28 @@
+use crate::value::Value;
+
+/// Chama a função `name` de um módulo criado por `create_module`.
+pub fn call(module: &Value, name: &str, args: Vec<Value>) -> Result<Value, String> {
+    match module {
+        Value::Dict(map) => match map.get(&Value::String(name.to_string())) {
+            Some(Value::NativeFunction(function)) => function(args),
+            _ => panic!("função {} ausente", name),
+        },
+        _ => panic!("módulo inválido"),
+    }
+}
+
+pub fn numbers(items: impl IntoIterator<Item = f64>) -> Value {
+    Value::List(items.into_iter().map(Value::Number).collect())
+}
+
+pub fn text(value: &str) -> Value {
+    Value::String(value.to_string())
+}
+
+/// Caminho de teste exclusivo deste processo, removido se já existir.
+pub fn scratch(name: &str) -> String {
+    let path = std::env::temp_dir().join(format!("snask-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&path);
+    let _ = std::fs::remove_file(&path);
+    path.to_string_lossy().into_owned()
+}
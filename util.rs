@@ -0,0 +1,29 @@
+use crate::value::Value;
+use std::path::{Path, PathBuf};
+
+/// Converte um caminho para string, com erro (em vez de descartá-lo) se não for UTF-8.
+pub fn path_to_string(path: &Path) -> Result<String, String> {
+    path.to_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("caminho não é UTF-8 válido: {}", path.to_string_lossy()))
+}
+
+/// Pasta pessoal do usuário (`HOME`), se definida.
+pub fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Bytes são representados como uma lista de números inteiros de 0 a 255.
+pub fn bytes_to_value(bytes: &[u8]) -> Value {
+    Value::List(bytes.iter().map(|b| Value::Number(*b as f64)).collect())
+}
+
+pub fn value_to_bytes(name: &str, value: &Value) -> Result<Vec<u8>, String> {
+    match value {
+        Value::List(list) => list.iter().map(|item| match item {
+            Value::Number(n) if n.fract() == 0.0 && (0.0..=255.0).contains(n) => Ok(*n as u8),
+            _ => Err(format!("{} espera uma lista de bytes (inteiros de 0 a 255)", name)),
+        }).collect(),
+        _ => Err(format!("{} espera uma lista de bytes (inteiros de 0 a 255)", name)),
+    }
+}
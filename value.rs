@@ -0,0 +1,106 @@
+use crate::collections::Handle;
+use indexmap::IndexMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Declaração de uma função Snask; o corpo é executado pelo interpretador.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<String>,
+}
+
+/// Valor em tempo de execução da linguagem Snask.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+    /// Dicionário que guarda as chaves na ordem de inserção.
+    Dict(IndexMap<Value, Value>),
+    Function(FunctionDecl),
+    NativeFunction(fn(Vec<Value>) -> Result<Value, String>),
+    /// Objeto nativo mutável (conjunto, deque, arquivo...) compartilhado entre cópias.
+    Handle(Handle),
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Dict(a), Value::Dict(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => std::ptr::fn_addr_eq(*a, *b),
+            (Value::Handle(a), Value::Handle(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// NaN nunca é igual a si mesmo; chaves NaN simplesmente não são encontradas.
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Nil => {},
+            Value::Boolean(b) => b.hash(state),
+            // 0.0 e -0.0 são iguais, então precisam do mesmo hash.
+            Value::Number(n) => (if *n == 0.0 { 0.0f64 } else { *n }).to_bits().hash(state),
+            Value::String(s) => s.hash(state),
+            Value::List(list) => list.hash(state),
+            // Dicionários iguais podem ter ordens diferentes: combina os hashes das entradas.
+            Value::Dict(dict) => {
+                let mut combined = 0u64;
+                for (key, value) in dict {
+                    let mut entry = std::collections::hash_map::DefaultHasher::new();
+                    key.hash(&mut entry);
+                    value.hash(&mut entry);
+                    combined = combined.wrapping_add(entry.finish());
+                }
+                dict.len().hash(state);
+                combined.hash(state);
+            },
+            Value::Function(func) => func.name.hash(state),
+            Value::NativeFunction(func) => (*func as usize).hash(state),
+            Value::Handle(handle) => handle.hash(state),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Value::Dict(dict) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in dict.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                write!(f, "}}")
+            },
+            Value::Function(func) => write!(f, "<função {}>", func.name),
+            Value::NativeFunction(_) => write!(f, "<função nativa>"),
+            Value::Handle(handle) => write!(f, "{}", handle),
+        }
+    }
+}
@@ -0,0 +1,1238 @@
+//! Sistema de arquivos usado pelo módulo `io`.
+//!
+//! Por padrão as funções vão direto ao disco (`DiskFs`). O programa hospedeiro pode
+//! instalar outro backend com `set_filesystem`, como faz com `set_function_caller`:
+//! - `MemoryFs`: árvore em memória, com injeção de falhas e limite de capacidade,
+//!   para testar scripts sem tocar no disco;
+//! - `ChrootFs`: disco real confinado a um diretório, que passa a ser a raiz `/`.
+//!
+//! Arquivos abertos, travas, temporários e gravações atômicas também passam pelo
+//! backend. Exceções: a lixeira de `io.delete` e o inotify de `io.watch` só valem
+//! para o disco direto (`is_disk`), e os campos de `stat` do sistema operacional e
+//! a proteção de `io.delete` usam `host_path`.
+
+use fs2::FileExt;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::OsString;
+use std::fs;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+/// Metadados comuns a todos os backends.
+#[derive(Clone, Debug)]
+pub struct FileInfo {
+    pub kind: FileKind,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub readonly: bool,
+    /// Identidade do arquivo no disco, `(dispositivo, inode)`: dois caminhos com o
+    /// mesmo id são o mesmo arquivo (ex.: links físicos). `None` se o backend não souber.
+    pub id: Option<(u64, u64)>,
+}
+
+impl FileInfo {
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileKind::Symlink
+    }
+}
+
+impl From<&fs::Metadata> for FileInfo {
+    fn from(metadata: &fs::Metadata) -> Self {
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        };
+        FileInfo {
+            kind,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            readonly: metadata.permissions().readonly(),
+            id: file_id(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Como `FileSystem::open` abre um arquivo; os campos são os de `fs::OpenOptions`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OpenMode {
+    pub read: bool,
+    pub write: bool,
+    pub append: bool,
+    pub create: bool,
+    pub truncate: bool,
+    pub create_new: bool,
+}
+
+impl OpenMode {
+    fn writes(&self) -> bool {
+        self.write || self.append
+    }
+}
+
+/// Arquivo aberto por um backend.
+pub trait FileStream: Read + Write + Seek {
+    /// Garante que o conteúdo chegue ao meio físico (`fsync`).
+    fn sync(&mut self) -> Result<()>;
+}
+
+impl FileStream for fs::File {
+    fn sync(&mut self) -> Result<()> {
+        self.sync_all()
+    }
+}
+
+/// Trava consultiva obtida com `FileSystem::lock`; é liberada ao ser descartada.
+pub trait LockGuard {
+    /// Libera a trava antes do descarte, informando erros.
+    fn unlock(&mut self) -> Result<()>;
+}
+
+/// Operações de arquivo usadas pelo módulo `io`. Os caminhos são os dos scripts;
+/// cada backend decide como interpretá-los.
+pub trait FileSystem {
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()>;
+    fn append(&self, path: &Path, data: &[u8]) -> Result<()>;
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn FileStream>>;
+    /// Segue links simbólicos.
+    fn metadata(&self, path: &Path) -> Result<FileInfo>;
+    /// Não segue links simbólicos.
+    fn symlink_metadata(&self, path: &Path) -> Result<FileInfo>;
+    /// Nomes das entradas, em ordem alfabética.
+    fn read_dir(&self, path: &Path) -> Result<Vec<OsString>>;
+    fn create_dir(&self, path: &Path) -> Result<()>;
+    fn create_dir_all(&self, path: &Path) -> Result<()>;
+    fn remove_file(&self, path: &Path) -> Result<()>;
+    /// Só remove diretórios vazios.
+    fn remove_dir(&self, path: &Path) -> Result<()>;
+    fn remove_dir_all(&self, path: &Path) -> Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+    /// Cria `link` apontando para `target`, que é gravado como está (relativo ao
+    /// diretório do link) e não precisa existir.
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()>;
+    fn hard_link(&self, src: &Path, dst: &Path) -> Result<()>;
+    fn read_link(&self, path: &Path) -> Result<PathBuf>;
+    /// Caminho absoluto com `.`, `..` e links resolvidos; o caminho precisa existir.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf>;
+    /// Altera a data de modificação (e a de acesso, se houver); segue links.
+    fn set_times(&self, path: &Path, modified: SystemTime, accessed: Option<SystemTime>) -> Result<()>;
+    /// Trava `path`, criado vazio se não existir. Sem `wait`, falha com `WouldBlock`
+    /// se a trava estiver ocupada.
+    fn lock(&self, path: &Path, shared: bool, wait: bool) -> Result<Box<dyn LockGuard>>;
+    /// Cria `prefix + aleatório + suffix` em `dir` (padrão: o diretório temporário
+    /// do backend) sem sobrescrever nada, e retorna o caminho criado.
+    fn create_temp(&self, dir: Option<&Path>, prefix: &str, suffix: &str, is_dir: bool) -> Result<PathBuf>;
+
+    /// Como `rename`, mas só retorna depois que a troca de nomes está gravada
+    /// (no disco, sincroniza também o diretório).
+    fn rename_atomic(&self, from: &Path, to: &Path) -> Result<()> {
+        self.rename(from, to)
+    }
+
+    /// Copia o conteúdo de um arquivo, criando ou truncando `dst`.
+    fn copy_file(&self, src: &Path, dst: &Path) -> Result<()> {
+        self.write(dst, &self.read(src)?)
+    }
+
+    /// Aplica a `dst` as permissões de `src`. Backends sem permissões não fazem nada.
+    fn copy_permissions(&self, _src: &Path, _dst: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Caminho real no disco, para os metadados do sistema operacional e a proteção
+    /// de `io.delete`.
+    /// `None` se o backend não tiver disco por trás.
+    fn host_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Rc<dyn FileSystem>>> = const { RefCell::new(None) };
+}
+
+/// Instala o sistema de arquivos usado pelo módulo `io` nesta thread.
+pub fn set_filesystem(filesystem: Rc<dyn FileSystem>) {
+    CURRENT.with(|current| *current.borrow_mut() = Some(filesystem));
+}
+
+/// Volta a usar o disco diretamente.
+pub fn clear_filesystem() {
+    CURRENT.with(|current| *current.borrow_mut() = None);
+}
+
+pub fn current() -> Rc<dyn FileSystem> {
+    CURRENT.with(|current| current.borrow().clone()).unwrap_or_else(|| Rc::new(DiskFs))
+}
+
+/// Se nenhum backend foi instalado e os caminhos dos scripts são os do disco.
+pub fn is_disk() -> bool {
+    CURRENT.with(|current| current.borrow().is_none())
+}
+
+/// Resolve `.` e `..` sem consultar o disco, a partir da raiz `/`.
+fn absolute_virtual(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::from("/");
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::ParentDir => { resolved.pop(); },
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {},
+        }
+    }
+    resolved
+}
+
+/// Nome da tentativa `attempt` de `create_temp`.
+fn temp_name(prefix: &str, suffix: &str, attempt: u64) -> Result<String> {
+    use std::hash::{BuildHasher, Hasher};
+
+    if prefix.contains(['/', '\\']) || suffix.contains(['/', '\\']) {
+        return Err(Error::new(ErrorKind::InvalidInput, "prefixo e sufixo não podem conter separadores"));
+    }
+    // RandomState é semeado aleatoriamente a cada instância.
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(attempt);
+    hasher.write_u32(std::process::id());
+    Ok(format!("{}{:012x}{}", prefix, hasher.finish() & 0xffff_ffff_ffff, suffix))
+}
+
+/// Acesso direto ao disco, com a mesma semântica de `std::fs`.
+pub struct DiskFs;
+
+impl FileSystem for DiskFs {
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        fs::write(path, data)
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+        fs::OpenOptions::new().create(true).append(true).open(path)?.write_all(data)
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn FileStream>> {
+        let file = fs::OpenOptions::new()
+            .read(mode.read)
+            .write(mode.write)
+            .append(mode.append)
+            .create(mode.create)
+            .truncate(mode.truncate)
+            .create_new(mode.create_new)
+            .open(path)?;
+        Ok(Box::new(file))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileInfo> {
+        fs::metadata(path).map(|metadata| FileInfo::from(&metadata))
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<FileInfo> {
+        fs::symlink_metadata(path).map(|metadata| FileInfo::from(&metadata))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<OsString>> {
+        let mut names = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>>>()?;
+        names.sort();
+        Ok(names)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        fs::create_dir(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        fs::remove_dir(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        fs::remove_dir_all(path)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        create_symlink(target, link)
+    }
+
+    fn hard_link(&self, src: &Path, dst: &Path) -> Result<()> {
+        fs::hard_link(src, dst)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        fs::read_link(path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        fs::canonicalize(path)
+    }
+
+    fn set_times(&self, path: &Path, modified: SystemTime, accessed: Option<SystemTime>) -> Result<()> {
+        let mut times = fs::FileTimes::new().set_modified(modified);
+        if let Some(accessed) = accessed {
+            times = times.set_accessed(accessed);
+        }
+        // Diretórios também podem ser abertos só para leitura.
+        fs::File::open(path)?.set_times(times)
+    }
+
+    fn lock(&self, path: &Path, shared: bool, wait: bool) -> Result<Box<dyn LockGuard>> {
+        let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        match (wait, shared) {
+            (true, false) => FileExt::lock_exclusive(&file),
+            (true, true) => FileExt::lock_shared(&file),
+            (false, false) => FileExt::try_lock_exclusive(&file),
+            (false, true) => FileExt::try_lock_shared(&file),
+        }?;
+        Ok(Box::new(DiskLock { file }))
+    }
+
+    /// Temporários ficam acessíveis só pelo dono no Unix.
+    fn create_temp(&self, dir: Option<&Path>, prefix: &str, suffix: &str, is_dir: bool) -> Result<PathBuf> {
+        let base = dir.map_or_else(std::env::temp_dir, Path::to_path_buf);
+        let mut attempt = 0;
+        loop {
+            let path = base.join(temp_name(prefix, suffix, attempt)?);
+            let created = if is_dir {
+                let mut builder = fs::DirBuilder::new();
+                #[cfg(unix)]
+                std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+                builder.create(&path)
+            } else {
+                let mut options = fs::OpenOptions::new();
+                options.write(true).create_new(true);
+                #[cfg(unix)]
+                std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+                options.open(&path).map(|_| ())
+            };
+            match created {
+                Ok(_) => return Ok(path),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn rename_atomic(&self, from: &Path, to: &Path) -> Result<()> {
+        fs::rename(from, to)?;
+        #[cfg(unix)]
+        {
+            let dir = match to.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn copy_file(&self, src: &Path, dst: &Path) -> Result<()> {
+        let mut input = fs::File::open(src)?;
+        let mut output = fs::File::create(dst)?;
+        std::io::copy(&mut input, &mut output).map(|_| ())
+    }
+
+    fn copy_permissions(&self, src: &Path, dst: &Path) -> Result<()> {
+        fs::set_permissions(dst, fs::metadata(src)?.permissions())
+    }
+
+    fn host_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+}
+
+/// Trava de `DiskFs`: um `flock` (ou `LockFileEx`) sobre o arquivo aberto.
+struct DiskLock {
+    file: fs::File,
+}
+
+impl LockGuard for DiskLock {
+    fn unlock(&mut self) -> Result<()> {
+        FileExt::unlock(&self.file)
+    }
+}
+
+impl Drop for DiskLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// O Windows distingue links para arquivos e para diretórios.
+#[cfg(windows)]
+fn create_symlink(target: &Path, link: &Path) -> Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new("")).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(_target: &Path, _link: &Path) -> Result<()> {
+    Err(Error::new(ErrorKind::Unsupported, "links simbólicos não são suportados nesta plataforma"))
+}
+
+/// Disco real confinado a `root`: `/` nos scripts é `root` no disco, caminhos
+/// relativos partem de `/` e `..` não sobe além da raiz. Links simbólicos que
+/// apontem para fora da raiz são recusados com `PermissionDenied`.
+///
+/// Não é uma barreira de segurança: os caminhos são checados antes de cada
+/// operação, e um link trocado por outro processo entre a checagem e o uso
+/// (TOCTOU) escapa da raiz. Serve para dar aos scripts uma árvore própria,
+/// não para isolar código hostil.
+pub struct ChrootFs {
+    root: PathBuf,
+}
+
+impl ChrootFs {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = fs::canonicalize(root)?;
+        if !root.is_dir() {
+            return Err(Error::new(ErrorKind::NotADirectory, "a raiz precisa ser um diretório"));
+        }
+        Ok(ChrootFs { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn map(&self, path: &Path) -> Result<PathBuf> {
+        let virtual_path = absolute_virtual(path);
+        let host = self.root.join(virtual_path.strip_prefix("/").unwrap_or(&virtual_path));
+
+        // O ancestral existente mais próximo, com links resolvidos, precisa continuar dentro da raiz.
+        let inside = host.ancestors()
+            .find_map(|ancestor| fs::canonicalize(ancestor).ok())
+            .is_some_and(|real| real.starts_with(&self.root));
+        if !inside {
+            return Err(outside_root(path));
+        }
+        Ok(host)
+    }
+
+    /// Como `map`, mas sem seguir o último componente: operações sobre o próprio
+    /// link (lstat, remover, ler o alvo) valem mesmo que ele aponte para fora.
+    fn map_link(&self, path: &Path) -> Result<PathBuf> {
+        let virtual_path = absolute_virtual(path);
+        match (virtual_path.parent(), virtual_path.file_name()) {
+            (Some(parent), Some(name)) => Ok(self.map(parent)?.join(name)),
+            _ => self.map(&virtual_path),
+        }
+    }
+
+    /// Inverso de `map`: como um caminho real aparece para os scripts.
+    fn guest_path(&self, host: &Path, path: &Path) -> Result<PathBuf> {
+        host.strip_prefix(&self.root)
+            .map(|relative| Path::new("/").join(relative))
+            .map_err(|_| outside_root(path))
+    }
+}
+
+fn outside_root(path: &Path) -> Error {
+    Error::new(ErrorKind::PermissionDenied, format!("{} aponta para fora da raiz", path.display()))
+}
+
+impl FileSystem for ChrootFs {
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        DiskFs.read(&self.map(path)?)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        DiskFs.write(&self.map(path)?, data)
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+        DiskFs.append(&self.map(path)?, data)
+    }
+
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn FileStream>> {
+        DiskFs.open(&self.map(path)?, mode)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileInfo> {
+        DiskFs.metadata(&self.map(path)?)
+    }
+
+    fn symlink_metadata(&self, path: &Path) -> Result<FileInfo> {
+        DiskFs.symlink_metadata(&self.map_link(path)?)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<OsString>> {
+        DiskFs.read_dir(&self.map(path)?)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        DiskFs.create_dir(&self.map(path)?)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        DiskFs.create_dir_all(&self.map(path)?)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        DiskFs.remove_file(&self.map_link(path)?)
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        DiskFs.remove_dir(&self.map(path)?)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        let host = self.map(path)?;
+        if host == self.root {
+            return Err(Error::new(ErrorKind::PermissionDenied, "não é possível apagar a raiz"));
+        }
+        DiskFs.remove_dir_all(&host)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        DiskFs.rename(&self.map_link(from)?, &self.map_link(to)?)
+    }
+
+    fn symlink(&self, target: &Path, link: &Path) -> Result<()> {
+        // Alvos absolutos partem da raiz. Relativos ficam como estão, mas não podem
+        // subir com `..` acima dela a partir do diretório real do link; links no
+        // meio do alvo são checados a cada acesso por `map`.
+        let link_host = self.map_link(link)?;
+        let target = if target.has_root() {
+            let virtual_target = absolute_virtual(target);
+            self.root.join(virtual_target.strip_prefix("/").unwrap_or(&virtual_target))
+        } else {
+            let parent = fs::canonicalize(link_host.parent().unwrap_or(&self.root))?;
+            let mut depth = parent.strip_prefix(&self.root).map_or(0, |relative| relative.components().count());
+            for component in target.components() {
+                match component {
+                    Component::ParentDir if depth == 0 => return Err(outside_root(target)),
+                    Component::ParentDir => depth -= 1,
+                    Component::Normal(_) => depth += 1,
+                    _ => {},
+                }
+            }
+            target.to_path_buf()
+        };
+        DiskFs.symlink(&target, &link_host)
+    }
+
+    fn hard_link(&self, src: &Path, dst: &Path) -> Result<()> {
+        DiskFs.hard_link(&self.map_link(src)?, &self.map_link(dst)?)
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        let target = DiskFs.read_link(&self.map_link(path)?)?;
+        if !target.is_absolute() {
+            return Ok(target);
+        }
+        self.guest_path(&target, path)
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        let real = DiskFs.canonicalize(&self.map(path)?)?;
+        self.guest_path(&real, path)
+    }
+
+    fn set_times(&self, path: &Path, modified: SystemTime, accessed: Option<SystemTime>) -> Result<()> {
+        DiskFs.set_times(&self.map(path)?, modified, accessed)
+    }
+
+    fn lock(&self, path: &Path, shared: bool, wait: bool) -> Result<Box<dyn LockGuard>> {
+        DiskFs.lock(&self.map(path)?, shared, wait)
+    }
+
+    /// O diretório temporário padrão é o `/tmp` dentro da raiz.
+    fn create_temp(&self, dir: Option<&Path>, prefix: &str, suffix: &str, is_dir: bool) -> Result<PathBuf> {
+        let dir = dir.unwrap_or(Path::new("/tmp"));
+        let host = DiskFs.create_temp(Some(&self.map(dir)?), prefix, suffix, is_dir)?;
+        self.guest_path(&host, dir)
+    }
+
+    fn rename_atomic(&self, from: &Path, to: &Path) -> Result<()> {
+        DiskFs.rename_atomic(&self.map_link(from)?, &self.map_link(to)?)
+    }
+
+    fn copy_file(&self, src: &Path, dst: &Path) -> Result<()> {
+        DiskFs.copy_file(&self.map(src)?, &self.map(dst)?)
+    }
+
+    fn copy_permissions(&self, src: &Path, dst: &Path) -> Result<()> {
+        DiskFs.copy_permissions(&self.map(src)?, &self.map(dst)?)
+    }
+
+    fn host_path(&self, path: &Path) -> Option<PathBuf> {
+        self.map(path).ok()
+    }
+}
+
+enum NodeData {
+    File(Vec<u8>),
+    Dir,
+}
+
+struct Node {
+    data: NodeData,
+    modified: SystemTime,
+    readonly: bool,
+}
+
+impl Node {
+    fn new(data: NodeData) -> Self {
+        Node { data, modified: SystemTime::now(), readonly: false }
+    }
+
+    fn len(&self) -> u64 {
+        match &self.data {
+            NodeData::File(bytes) => bytes.len() as u64,
+            NodeData::Dir => 0,
+        }
+    }
+
+    fn info(&self) -> FileInfo {
+        let kind = match self.data {
+            NodeData::File(_) => FileKind::File,
+            NodeData::Dir => FileKind::Dir,
+        };
+        FileInfo { kind, len: self.len(), modified: Some(self.modified), accessed: None, readonly: self.readonly, id: None }
+    }
+}
+
+/// Falha injetada: operações `op` ("read", "write", "append", "metadata", "read_dir",
+/// "create_dir", "remove", "rename", "lock" ou "*" para todas) em `path` ou abaixo dele.
+struct Fault {
+    op: String,
+    path: PathBuf,
+    kind: ErrorKind,
+}
+
+/// Trava de `MemoryFs`: contagem de leitores ou um único dono exclusivo.
+enum MemoryLockState {
+    Shared(usize),
+    Exclusive,
+}
+
+struct MemoryState {
+    nodes: BTreeMap<PathBuf, Node>,
+    faults: Vec<Fault>,
+    capacity: Option<u64>,
+    /// Soma dos tamanhos de todos os arquivos, mantida a cada alteração.
+    used: u64,
+    locks: HashMap<PathBuf, MemoryLockState>,
+}
+
+impl MemoryState {
+    fn check_fault(&self, op: &str, path: &Path) -> Result<()> {
+        match self.faults.iter().find(|fault| (fault.op == "*" || fault.op == op) && path.starts_with(&fault.path)) {
+            Some(fault) => Err(Error::new(fault.kind, format!("falha injetada em {} ({})", path.display(), op))),
+            None => Ok(()),
+        }
+    }
+
+    /// O diretório pai precisa existir e aceitar alterações.
+    fn writable_parent(&self, path: &Path) -> Result<()> {
+        let parent = path.parent().ok_or_else(|| Error::new(ErrorKind::PermissionDenied, "a raiz não pode ser alterada"))?;
+        match self.nodes.get(parent) {
+            Some(Node { data: NodeData::Dir, readonly: false, .. }) => Ok(()),
+            Some(Node { data: NodeData::Dir, .. }) => Err(permission_denied(parent)),
+            Some(_) => Err(Error::new(ErrorKind::NotADirectory, format!("{} não é um diretório", parent.display()))),
+            None => Err(not_found(parent)),
+        }
+    }
+
+    /// Tamanho atual do arquivo gravável em `path`, ou `None` se ele ainda não existe.
+    fn writable_file(&self, path: &Path) -> Result<Option<u64>> {
+        match self.nodes.get(path) {
+            Some(Node { data: NodeData::Dir, .. }) => Err(is_a_directory(path)),
+            Some(Node { readonly: true, .. }) => Err(permission_denied(path)),
+            Some(node) => Ok(Some(node.len())),
+            None => self.writable_parent(path).map(|_| None),
+        }
+    }
+
+    /// Reserva espaço para o arquivo `path` passar de `old_len` para `new_len` bytes.
+    fn reserve(&mut self, old_len: u64, new_len: u64) -> Result<()> {
+        let used = self.used - old_len + new_len;
+        if self.capacity.is_some_and(|capacity| used > capacity) {
+            return Err(Error::new(ErrorKind::StorageFull, "sem espaço no sistema de arquivos em memória"));
+        }
+        self.used = used;
+        Ok(())
+    }
+
+    /// Grava `data` na posição `offset` (`None` = no fim), criando o arquivo se preciso.
+    fn write_at(&mut self, path: &Path, offset: Option<u64>, data: &[u8], truncate: bool) -> Result<u64> {
+        let old_len = self.writable_file(path)?.unwrap_or(0);
+        let kept = if truncate { 0 } else { old_len };
+        let start = offset.unwrap_or(kept);
+        let new_len = kept.max(start + data.len() as u64);
+        self.reserve(old_len, new_len)?;
+
+        let node = self.nodes.entry(path.to_path_buf()).or_insert_with(|| Node::new(NodeData::File(Vec::new())));
+        if let NodeData::File(bytes) = &mut node.data {
+            bytes.truncate(kept as usize);
+            bytes.resize(new_len as usize, 0);
+            bytes[start as usize..start as usize + data.len()].copy_from_slice(data);
+        }
+        node.modified = SystemTime::now();
+        Ok(start + data.len() as u64)
+    }
+
+    fn remove_node(&mut self, path: &Path) {
+        if let Some(node) = self.nodes.remove(path) {
+            self.used -= node.len();
+        }
+    }
+
+    fn children<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a PathBuf> + 'a {
+        self.nodes.range(path.to_path_buf()..)
+            .skip(1)
+            .take_while(move |(key, _)| key.starts_with(path))
+            .filter(move |(key, _)| key.parent() == Some(path))
+            .map(|(key, _)| key)
+    }
+}
+
+/// Árvore de arquivos em memória. Caminhos relativos partem de `/`; não há links.
+/// Arquivos e diretórios somente leitura recusam alterações com `PermissionDenied`,
+/// e gravações além de `capacity` bytes falham com `StorageFull` (disco cheio).
+pub struct MemoryFs {
+    state: Rc<RefCell<MemoryState>>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(PathBuf::from("/"), Node::new(NodeData::Dir));
+        let state = MemoryState { nodes, faults: Vec::new(), capacity: None, used: 0, locks: HashMap::new() };
+        MemoryFs { state: Rc::new(RefCell::new(state)) }
+    }
+
+    /// Cria um arquivo (e os diretórios acima dele), ignorando falhas e capacidade.
+    pub fn add_file(&self, path: impl AsRef<Path>, data: impl Into<Vec<u8>>) {
+        let path = absolute_virtual(path.as_ref());
+        let mut state = self.state.borrow_mut();
+        for ancestor in path.ancestors().skip(1) {
+            state.nodes.entry(ancestor.to_path_buf()).or_insert_with(|| Node::new(NodeData::Dir));
+        }
+        state.remove_node(&path);
+        let node = Node::new(NodeData::File(data.into()));
+        state.used += node.len();
+        state.nodes.insert(path, node);
+    }
+
+    pub fn add_dir(&self, path: impl AsRef<Path>) {
+        let path = absolute_virtual(path.as_ref());
+        let mut state = self.state.borrow_mut();
+        for ancestor in path.ancestors() {
+            state.nodes.entry(ancestor.to_path_buf()).or_insert_with(|| Node::new(NodeData::Dir));
+        }
+    }
+
+    /// Faz `op` falhar com `kind` em `path` e abaixo dele até `clear_faults`.
+    pub fn inject_fault(&self, op: &str, path: impl AsRef<Path>, kind: ErrorKind) {
+        let path = absolute_virtual(path.as_ref());
+        self.state.borrow_mut().faults.push(Fault { op: op.to_string(), path, kind });
+    }
+
+    pub fn clear_faults(&self) {
+        self.state.borrow_mut().faults.clear();
+    }
+
+    /// Limite de bytes somados de todos os arquivos; `None` = sem limite.
+    pub fn set_capacity(&self, capacity: Option<u64>) {
+        self.state.borrow_mut().capacity = capacity;
+    }
+
+    pub fn set_readonly(&self, path: impl AsRef<Path>, readonly: bool) -> Result<()> {
+        let path = absolute_virtual(path.as_ref());
+        match self.state.borrow_mut().nodes.get_mut(&path) {
+            Some(node) => {
+                node.readonly = readonly;
+                Ok(())
+            },
+            None => Err(not_found(&path)),
+        }
+    }
+
+    /// Bytes ocupados por todos os arquivos.
+    pub fn used_bytes(&self) -> u64 {
+        self.state.borrow().used
+    }
+
+    /// Caminho absoluto de `path`, depois de checar as falhas injetadas para `op`.
+    fn resolve(&self, op: &str, path: &Path) -> Result<PathBuf> {
+        let path = absolute_virtual(path);
+        self.state.borrow().check_fault(op, &path)?;
+        Ok(path)
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::new(ErrorKind::NotFound, format!("{} não existe", path.display()))
+}
+
+fn permission_denied(path: &Path) -> Error {
+    Error::new(ErrorKind::PermissionDenied, format!("{} é somente leitura", path.display()))
+}
+
+fn links_unsupported() -> Error {
+    Error::new(ErrorKind::Unsupported, "o sistema de arquivos em memória não tem links")
+}
+
+fn is_a_directory(path: &Path) -> Error {
+    Error::new(ErrorKind::IsADirectory, format!("{} é um diretório", path.display()))
+}
+
+impl FileSystem for MemoryFs {
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let path = self.resolve("read", path)?;
+        match self.state.borrow().nodes.get(&path) {
+            Some(Node { data: NodeData::File(bytes), .. }) => Ok(bytes.clone()),
+            Some(_) => Err(is_a_directory(&path)),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let path = self.resolve("write", path)?;
+        self.state.borrow_mut().write_at(&path, Some(0), data, true).map(|_| ())
+    }
+
+    fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let path = self.resolve("append", path)?;
+        self.state.borrow_mut().write_at(&path, None, data, false).map(|_| ())
+    }
+
+    /// O arquivo aberto é lido e escrito direto na árvore; se for removido ou
+    /// renomeado, as operações seguintes falham com `NotFound`.
+    fn open(&self, path: &Path, mode: OpenMode) -> Result<Box<dyn FileStream>> {
+        let path = self.resolve(if mode.writes() { "write" } else { "read" }, path)?;
+        let mut state = self.state.borrow_mut();
+        match state.nodes.get(&path) {
+            Some(_) if mode.create_new => {
+                return Err(Error::new(ErrorKind::AlreadyExists, format!("{} já existe", path.display())));
+            },
+            Some(Node { data: NodeData::Dir, .. }) => return Err(is_a_directory(&path)),
+            Some(Node { readonly: true, .. }) if mode.writes() => return Err(permission_denied(&path)),
+            Some(_) if mode.truncate => { state.write_at(&path, Some(0), &[], true)?; },
+            Some(_) => {},
+            None if mode.create || mode.create_new => { state.write_at(&path, None, &[], false)?; },
+            None => return Err(not_found(&path)),
+        }
+        Ok(Box::new(MemoryFile { state: Rc::clone(&self.state), path, mode, position: 0 }))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<FileInfo> {
+        let path = self.resolve("metadata", path)?;
+        self.state.borrow().nodes.get(&path).map(Node::info).ok_or_else(|| not_found(&path))
+    }
+
+    /// Sem links, o próprio nó: não há nada a seguir nem a deixar de seguir.
+    fn symlink_metadata(&self, path: &Path) -> Result<FileInfo> {
+        self.metadata(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<OsString>> {
+        let path = self.resolve("read_dir", path)?;
+        let state = self.state.borrow();
+        match state.nodes.get(&path) {
+            Some(Node { data: NodeData::Dir, .. }) => Ok(state.children(&path)
+                .filter_map(|child| child.file_name().map(|name| name.to_os_string()))
+                .collect()),
+            Some(_) => Err(Error::new(ErrorKind::NotADirectory, format!("{} não é um diretório", path.display()))),
+            None => Err(not_found(&path)),
+        }
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        let path = self.resolve("create_dir", path)?;
+        let mut state = self.state.borrow_mut();
+        if state.nodes.contains_key(&path) {
+            return Err(Error::new(ErrorKind::AlreadyExists, format!("{} já existe", path.display())));
+        }
+        state.writable_parent(&path)?;
+        state.nodes.insert(path, Node::new(NodeData::Dir));
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<()> {
+        let path = self.resolve("create_dir", path)?;
+        let missing: Vec<PathBuf> = {
+            let state = self.state.borrow();
+            if let Some(node) = state.nodes.get(&path) {
+                return match node.data {
+                    NodeData::Dir => Ok(()),
+                    NodeData::File(_) => Err(Error::new(ErrorKind::AlreadyExists, format!("{} já existe", path.display()))),
+                };
+            }
+            path.ancestors().take_while(|ancestor| !state.nodes.contains_key(*ancestor)).map(Path::to_path_buf).collect()
+        };
+        for dir in missing.iter().rev() {
+            self.create_dir(dir)?;
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        let path = self.resolve("remove", path)?;
+        let mut state = self.state.borrow_mut();
+        match state.nodes.get(&path) {
+            Some(Node { data: NodeData::Dir, .. }) => return Err(is_a_directory(&path)),
+            Some(_) => state.writable_parent(&path)?,
+            None => return Err(not_found(&path)),
+        }
+        state.remove_node(&path);
+        Ok(())
+    }
+
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        let path = self.resolve("remove", path)?;
+        let mut state = self.state.borrow_mut();
+        match state.nodes.get(&path) {
+            Some(Node { data: NodeData::Dir, .. }) => {},
+            Some(_) => return Err(Error::new(ErrorKind::NotADirectory, format!("{} não é um diretório", path.display()))),
+            None => return Err(not_found(&path)),
+        }
+        if state.children(&path).next().is_some() {
+            return Err(Error::new(ErrorKind::DirectoryNotEmpty, format!("{} não está vazio", path.display())));
+        }
+        state.writable_parent(&path)?;
+        state.remove_node(&path);
+        Ok(())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        let path = self.resolve("remove", path)?;
+        let mut state = self.state.borrow_mut();
+        if !state.nodes.contains_key(&path) {
+            return Err(not_found(&path));
+        }
+        state.writable_parent(&path)?;
+        let doomed: Vec<PathBuf> = state.nodes.keys().filter(|key| key.starts_with(&path)).cloned().collect();
+        // Como no disco, um diretório somente leitura impede apagar o que está dentro dele.
+        if let Some(locked) = doomed.iter().find(|key| **key != path && state.nodes[key.parent().unwrap()].readonly) {
+            return Err(permission_denied(locked.parent().unwrap()));
+        }
+        for key in doomed {
+            state.remove_node(&key);
+        }
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let from = self.resolve("rename", from)?;
+        let to = self.resolve("rename", to)?;
+        let mut state = self.state.borrow_mut();
+        let source_is_dir = match state.nodes.get(&from) {
+            Some(node) => matches!(node.data, NodeData::Dir),
+            None => return Err(not_found(&from)),
+        };
+        if from == to {
+            return Ok(());
+        }
+        if to.starts_with(&from) {
+            return Err(Error::new(ErrorKind::InvalidInput, "não é possível mover um diretório para dentro dele mesmo"));
+        }
+        state.writable_parent(&from)?;
+        state.writable_parent(&to)?;
+        match state.nodes.get(&to) {
+            Some(Node { data: NodeData::Dir, .. }) if !source_is_dir => return Err(is_a_directory(&to)),
+            Some(Node { data: NodeData::File(_), .. }) if source_is_dir => {
+                return Err(Error::new(ErrorKind::NotADirectory, format!("{} não é um diretório", to.display())));
+            },
+            Some(Node { data: NodeData::Dir, .. }) if state.children(&to).next().is_some() => {
+                return Err(Error::new(ErrorKind::DirectoryNotEmpty, format!("{} não está vazio", to.display())));
+            },
+            _ => {},
+        }
+
+        state.remove_node(&to);
+        let moved: Vec<PathBuf> = state.nodes.keys().filter(|key| key.starts_with(&from)).cloned().collect();
+        for key in moved {
+            let node = state.nodes.remove(&key).unwrap();
+            let relative = key.strip_prefix(&from).unwrap();
+            let target = if relative.as_os_str().is_empty() { to.clone() } else { to.join(relative) };
+            state.nodes.insert(target, node);
+        }
+        Ok(())
+    }
+
+    fn symlink(&self, _target: &Path, _link: &Path) -> Result<()> {
+        Err(links_unsupported())
+    }
+
+    fn hard_link(&self, _src: &Path, _dst: &Path) -> Result<()> {
+        Err(links_unsupported())
+    }
+
+    fn read_link(&self, path: &Path) -> Result<PathBuf> {
+        let path = self.resolve("metadata", path)?;
+        if self.state.borrow().nodes.contains_key(&path) {
+            Err(Error::new(ErrorKind::InvalidInput, format!("{} não é um link simbólico", path.display())))
+        } else {
+            Err(not_found(&path))
+        }
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        let path = self.resolve("metadata", path)?;
+        if self.state.borrow().nodes.contains_key(&path) {
+            Ok(path)
+        } else {
+            Err(not_found(&path))
+        }
+    }
+
+    fn set_times(&self, path: &Path, modified: SystemTime, _accessed: Option<SystemTime>) -> Result<()> {
+        let path = self.resolve("write", path)?;
+        match self.state.borrow_mut().nodes.get_mut(&path) {
+            Some(node) => {
+                node.modified = modified;
+                Ok(())
+            },
+            None => Err(not_found(&path)),
+        }
+    }
+
+    /// Sem outras threads para liberar a trava, esperar por uma trava ocupada
+    /// falharia para sempre: nesse caso o erro é `Deadlock`.
+    fn lock(&self, path: &Path, shared: bool, wait: bool) -> Result<Box<dyn LockGuard>> {
+        let path = self.resolve("lock", path)?;
+        let mut state = self.state.borrow_mut();
+        state.write_at(&path, None, &[], false)?;
+        let busy = match state.locks.get(&path) {
+            None => false,
+            Some(MemoryLockState::Shared(_)) => !shared,
+            Some(MemoryLockState::Exclusive) => true,
+        };
+        if busy {
+            let kind = if wait { ErrorKind::Deadlock } else { ErrorKind::WouldBlock };
+            return Err(Error::new(kind, format!("{} já está travado", path.display())));
+        }
+        match state.locks.entry(path.clone()).or_insert(MemoryLockState::Shared(0)) {
+            MemoryLockState::Shared(count) if shared => *count += 1,
+            lock => *lock = MemoryLockState::Exclusive,
+        }
+        Ok(Box::new(MemoryLock { state: Rc::clone(&self.state), path: Some(path) }))
+    }
+
+    /// O diretório temporário padrão é `/tmp`, que precisa existir.
+    fn create_temp(&self, dir: Option<&Path>, prefix: &str, suffix: &str, is_dir: bool) -> Result<PathBuf> {
+        let dir = dir.unwrap_or(Path::new("/tmp"));
+        let mut attempt = 0;
+        loop {
+            let path = self.resolve("write", &dir.join(temp_name(prefix, suffix, attempt)?))?;
+            if !self.state.borrow().nodes.contains_key(&path) {
+                if is_dir {
+                    self.create_dir(&path)?;
+                } else {
+                    self.state.borrow_mut().write_at(&path, None, &[], false)?;
+                }
+                return Ok(path);
+            }
+            attempt += 1;
+        }
+    }
+
+    fn copy_permissions(&self, src: &Path, dst: &Path) -> Result<()> {
+        let readonly = self.metadata(src)?.readonly;
+        self.set_readonly(dst, readonly)
+    }
+}
+
+/// Arquivo aberto de `MemoryFs`.
+struct MemoryFile {
+    state: Rc<RefCell<MemoryState>>,
+    path: PathBuf,
+    mode: OpenMode,
+    position: u64,
+}
+
+impl MemoryFile {
+    fn len(&self) -> Result<u64> {
+        match self.state.borrow().nodes.get(&self.path) {
+            Some(node) => Ok(node.len()),
+            None => Err(not_found(&self.path)),
+        }
+    }
+}
+
+impl Read for MemoryFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if !self.mode.read {
+            return Err(Error::new(ErrorKind::PermissionDenied, "arquivo não foi aberto para leitura"));
+        }
+        let state = self.state.borrow();
+        state.check_fault("read", &self.path)?;
+        let bytes = match state.nodes.get(&self.path) {
+            Some(Node { data: NodeData::File(bytes), .. }) => bytes,
+            Some(_) => return Err(is_a_directory(&self.path)),
+            None => return Err(not_found(&self.path)),
+        };
+        let start = (self.position as usize).min(bytes.len());
+        let count = buf.len().min(bytes.len() - start);
+        buf[..count].copy_from_slice(&bytes[start..start + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Write for MemoryFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if !self.mode.writes() {
+            return Err(Error::new(ErrorKind::PermissionDenied, "arquivo não foi aberto para escrita"));
+        }
+        let mut state = self.state.borrow_mut();
+        let op = if self.mode.append { "append" } else { "write" };
+        state.check_fault(op, &self.path)?;
+        if !state.nodes.contains_key(&self.path) {
+            return Err(not_found(&self.path));
+        }
+        let offset = if self.mode.append { None } else { Some(self.position) };
+        self.position = state.write_at(&self.path, offset, buf, false)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryFile {
+    fn seek(&mut self, position: SeekFrom) -> Result<u64> {
+        let target = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len()?.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = target.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "posição antes do início do arquivo"))?;
+        Ok(self.position)
+    }
+}
+
+impl FileStream for MemoryFile {
+    fn sync(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Trava de `MemoryFs`; `path` vira `None` depois de liberada.
+struct MemoryLock {
+    state: Rc<RefCell<MemoryState>>,
+    path: Option<PathBuf>,
+}
+
+impl LockGuard for MemoryLock {
+    fn unlock(&mut self) -> Result<()> {
+        let Some(path) = self.path.take() else { return Ok(()) };
+        let mut state = self.state.borrow_mut();
+        if let Some(MemoryLockState::Shared(count)) = state.locks.get_mut(&path) {
+            if *count > 1 {
+                *count -= 1;
+                return Ok(());
+            }
+        }
+        state.locks.remove(&path);
+        Ok(())
+    }
+}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+        let _ = self.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_fs_tracks_capacity_faults_and_locks() {
+        let memory = MemoryFs::new();
+        memory.add_file("/a.txt", "abc");
+        memory.set_capacity(Some(8));
+
+        let mut file = memory.open(Path::new("/a.txt"), OpenMode { write: true, append: true, ..OpenMode::default() }).unwrap();
+        file.write_all(b"de").unwrap();
+        assert_eq!(file.write_all(b"fghi").unwrap_err().kind(), ErrorKind::StorageFull);
+        assert_eq!(memory.read(Path::new("/a.txt")).unwrap(), b"abcde");
+        assert_eq!(memory.used_bytes(), 5);
+        memory.write(Path::new("/a.txt"), b"x").unwrap();
+        assert_eq!(memory.used_bytes(), 1);
+
+        memory.inject_fault("append", "/", ErrorKind::PermissionDenied);
+        assert_eq!(file.write_all(b"y").unwrap_err().kind(), ErrorKind::PermissionDenied);
+        memory.clear_faults();
+
+        let shared = memory.lock(Path::new("/a.txt"), true, false).unwrap();
+        assert!(memory.lock(Path::new("/a.txt"), true, false).is_ok());
+        assert_eq!(memory.lock(Path::new("/a.txt"), false, false).err().unwrap().kind(), ErrorKind::WouldBlock);
+        assert_eq!(memory.lock(Path::new("/a.txt"), false, true).err().unwrap().kind(), ErrorKind::Deadlock);
+        drop(shared);
+        assert!(memory.lock(Path::new("/a.txt"), false, false).is_ok());
+    }
+}